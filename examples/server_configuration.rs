@@ -1,7 +1,7 @@
-use fson::{generator, object, parser::parse, ReferenceAsValue, TemplateValue, Value};
+use fson::{generator, object, Path, ReferenceAsValue, TemplateValue, Value};
 fn main() {
-    // Generate FSON
-    let fson = generator::from(Value::Object(object!(
+    // Build FSON
+    let configuration = Value::Object(object!(
         String::from("server") => Value::Object(object!(
             String::from("host") => Value::ReferenceDeclaration {id: String::from("host"), value: Box::new(Value::String(String::from("localhost")))},
             String::from("protocol") => Value::ReferenceDeclaration {id: String::from("protocol"), value: Box::new(Value::String(String::from("http")))},
@@ -14,13 +14,17 @@ fn main() {
             TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Id(String::from("host")))),
             TemplateValue::String(String::from(":")),
             TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Id(String::from("port")))),
-            TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Path(vec![
+            TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Path(Path::from(vec![
                 String::from("indexRoute")
-            ])))
+            ]))))
         ])
-    )));
+    ));
+
+    // Generate FSON
+    let fson = generator::from(configuration.clone());
+    println!("{fson}");
 
-    // Render it
-    let rendered = generator::from(parse(fson).unwrap());
+    // Evaluate references and template strings, then render it
+    let rendered = generator::from(configuration.resolve().unwrap());
     println!("{rendered}");
 }
@@ -0,0 +1,297 @@
+use crate::expression::{BinaryOperator, Expression, UnaryOperator};
+use crate::types::{IndexMap, ReferenceAsValue, Span, TemplateValue, Value, Variable};
+
+/// Node together with its place in the source.
+/// `span` is `None` for nodes that were not parsed from a string
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Option<Span>,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Option<Span>) -> Spanned<T> {
+        Spanned { node, span }
+    }
+}
+
+impl Spanned<Node> {
+    /// Converts node to a value without evaluating it, see [`Node::into_value`]
+    pub fn into_value(self) -> Value {
+        self.node.into_value()
+    }
+}
+
+impl From<Spanned<Node>> for Value {
+    fn from(node: Spanned<Node>) -> Value {
+        node.into_value()
+    }
+}
+
+/// Part of a template string: characters between interpolations or `${...}`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateNode {
+    String(String),
+    Interpolation(Spanned<Node>),
+    Variable(Variable),
+}
+
+/// Unevaluated expression, the node form of [`Expression`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionNode {
+    Unary {
+        operator: UnaryOperator,
+        operand: Spanned<Node>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Spanned<Node>,
+        right: Spanned<Node>,
+    },
+    Conditional {
+        condition: Spanned<Node>,
+        then: Spanned<Node>,
+        otherwise: Spanned<Node>,
+    },
+    Variable(Variable),
+    Call {
+        function: String,
+        arguments: Vec<Spanned<Node>>,
+    },
+}
+
+impl ExpressionNode {
+    /// Operands in the order they are written
+    pub fn operands(&self) -> Vec<&Spanned<Node>> {
+        match self {
+            ExpressionNode::Unary { operand, .. } => vec![operand],
+            ExpressionNode::Binary { left, right, .. } => vec![left, right],
+            ExpressionNode::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => vec![condition, then, otherwise],
+            ExpressionNode::Variable(_) => vec![],
+            ExpressionNode::Call { arguments, .. } => arguments.iter().collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Spanned<Node>> {
+        match self {
+            ExpressionNode::Unary { operand, .. } => vec![operand],
+            ExpressionNode::Binary { left, right, .. } => vec![left, right],
+            ExpressionNode::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => vec![condition, then, otherwise],
+            ExpressionNode::Variable(_) => vec![],
+            ExpressionNode::Call { arguments, .. } => arguments.iter_mut().collect(),
+        }
+    }
+
+    pub fn from_expression(expression: &Expression) -> ExpressionNode {
+        let node = |value: &Value| Spanned::new(Node::from_value(value), None);
+        match expression {
+            Expression::Unary { operator, operand } => ExpressionNode::Unary {
+                operator: *operator,
+                operand: node(operand),
+            },
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => ExpressionNode::Binary {
+                operator: *operator,
+                left: node(left),
+                right: node(right),
+            },
+            Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => ExpressionNode::Conditional {
+                condition: node(condition),
+                then: node(then),
+                otherwise: node(otherwise),
+            },
+            Expression::Variable(variable) => ExpressionNode::Variable(variable.clone()),
+            Expression::Call {
+                function,
+                arguments,
+            } => ExpressionNode::Call {
+                function: function.clone(),
+                arguments: arguments.iter().map(node).collect(),
+            },
+        }
+    }
+
+    pub fn into_expression(self) -> Expression {
+        match self {
+            ExpressionNode::Unary { operator, operand } => Expression::Unary {
+                operator,
+                operand: operand.into_value(),
+            },
+            ExpressionNode::Binary {
+                operator,
+                left,
+                right,
+            } => Expression::Binary {
+                operator,
+                left: left.into_value(),
+                right: right.into_value(),
+            },
+            ExpressionNode::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => Expression::Conditional {
+                condition: condition.into_value(),
+                then: then.into_value(),
+                otherwise: otherwise.into_value(),
+            },
+            ExpressionNode::Variable(variable) => Expression::Variable(variable),
+            ExpressionNode::Call {
+                function,
+                arguments,
+            } => Expression::Call {
+                function,
+                arguments: arguments
+                    .into_iter()
+                    .map(|argument| argument.into_value())
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Unevaluated document tree: references, template strings and
+/// reference declarations are kept as they were written
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Infinity,
+    NegativeInfinity,
+    Null,
+    NaN,
+    String(String),
+    Number(f64),
+    Integer(i128),
+    BigInteger(String),
+    Boolean(bool),
+    Object(Vec<(Spanned<String>, Spanned<Node>)>),
+    Array(Vec<Spanned<Node>>),
+    TemplateString(Vec<Spanned<TemplateNode>>),
+    Reference(ReferenceAsValue),
+    ReferenceDeclaration {
+        id: Spanned<String>,
+        value: Box<Spanned<Node>>,
+    },
+    Include(String),
+    Expression(Box<ExpressionNode>),
+}
+
+impl Node {
+    /// Converts value to a node without a span
+    pub fn from_value(value: &Value) -> Node {
+        match value {
+            Value::Infinity => Node::Infinity,
+            Value::NegativeInfinity => Node::NegativeInfinity,
+            Value::Null => Node::Null,
+            Value::NaN => Node::NaN,
+            Value::String(str) | Value::Identifier(str) => Node::String(str.clone()),
+            Value::Number(num) => Node::Number(*num),
+            Value::Integer(integer) => Node::Integer(*integer),
+            Value::BigInteger(integer) => Node::BigInteger(integer.clone()),
+            Value::Boolean(bool) => Node::Boolean(*bool),
+            Value::Object(hashmap) => Node::Object(
+                hashmap
+                    .iter()
+                    .map(|(k, v)| {
+                        (
+                            Spanned::new(k.clone(), None),
+                            Spanned::new(Node::from_value(v), None),
+                        )
+                    })
+                    .collect(),
+            ),
+            Value::Array(arr) => Node::Array(
+                arr.iter()
+                    .map(|e| Spanned::new(Node::from_value(e), None))
+                    .collect(),
+            ),
+            Value::TemplateString(parts) => Node::TemplateString(
+                parts
+                    .iter()
+                    .map(|part| {
+                        let part = match part {
+                            TemplateValue::String(str) => TemplateNode::String(str.clone()),
+                            TemplateValue::Interpolation(value) => TemplateNode::Interpolation(
+                                Spanned::new(Node::from_value(value), None),
+                            ),
+                            TemplateValue::Variable(variable) => {
+                                TemplateNode::Variable(variable.clone())
+                            }
+                        };
+                        Spanned::new(part, None)
+                    })
+                    .collect(),
+            ),
+            Value::Reference(reference) => Node::Reference(reference.clone()),
+            Value::ReferenceDeclaration { id, value } => Node::ReferenceDeclaration {
+                id: Spanned::new(id.clone(), None),
+                value: Box::new(Spanned::new(Node::from_value(value), None)),
+            },
+            Value::Include(name) => Node::Include(name.clone()),
+            Value::Expression(expression) => {
+                Node::Expression(Box::new(ExpressionNode::from_expression(expression)))
+            }
+        }
+    }
+
+    /// Converts node to a value without evaluating it:
+    /// references, template strings and reference declarations are kept
+    pub fn into_value(self) -> Value {
+        match self {
+            Node::Infinity => Value::Infinity,
+            Node::NegativeInfinity => Value::NegativeInfinity,
+            Node::Null => Value::Null,
+            Node::NaN => Value::NaN,
+            Node::String(str) => Value::String(str),
+            Node::Number(num) => Value::Number(num),
+            Node::Integer(integer) => Value::Integer(integer),
+            Node::BigInteger(integer) => Value::BigInteger(integer),
+            Node::Boolean(bool) => Value::Boolean(bool),
+            Node::Object(pairs) => {
+                let mut object: IndexMap<String, Value> = IndexMap::new();
+                for (key, value) in pairs {
+                    object.insert(key.node, value.node.into_value());
+                }
+                Value::Object(object)
+            }
+            Node::Array(arr) => {
+                Value::Array(arr.into_iter().map(|e| e.node.into_value()).collect())
+            }
+            Node::TemplateString(parts) => Value::TemplateString(
+                parts
+                    .into_iter()
+                    .map(|part| match part.node {
+                        TemplateNode::String(str) => TemplateValue::String(str),
+                        TemplateNode::Interpolation(value) => {
+                            TemplateValue::Interpolation(value.node.into_value())
+                        }
+                        TemplateNode::Variable(variable) => TemplateValue::Variable(variable),
+                    })
+                    .collect(),
+            ),
+            Node::Reference(reference) => Value::Reference(reference),
+            Node::ReferenceDeclaration { id, value } => Value::ReferenceDeclaration {
+                id: id.node,
+                value: Box::new(value.node.into_value()),
+            },
+            Node::Include(name) => Value::Include(name),
+            Node::Expression(expression) => {
+                Value::Expression(Box::new(expression.into_expression()))
+            }
+        }
+    }
+}
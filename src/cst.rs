@@ -0,0 +1,103 @@
+use crate::types::{Rule, Span};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Spaces, tabs and newlines
+    Whitespace,
+    /// `// ...` or `/* ... */`
+    Comment,
+    /// Fixed text of the grammar: brackets, commas, colons, quotes, `#id`, `${`, ...
+    Symbol,
+    /// Literal exactly as it was written: number, string characters, identifier, ...
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+impl CstToken {
+    /// Whitespace or comment
+    pub fn is_trivia(&self) -> bool {
+        self.kind == TokenKind::Whitespace || self.kind == TokenKind::Comment
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+/// Lossless syntax tree node: all of the source text is kept in tokens, so
+/// `node.to_string()` gives back the input it was parsed from byte-for-byte
+/// ## Example:
+/// ```
+/// let cst = parse_cst(String::from("{ x: 0x1ABC, /* comment */ }")).unwrap();
+/// cst.to_string() // "{ x: 0x1ABC, /* comment */ }"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    /// Grammar rule of the node, [`Rule::document`] for the root
+    pub rule: Rule,
+    pub span: Span,
+    pub children: Vec<CstElement>,
+}
+
+impl CstNode {
+    /// Child nodes, without tokens
+    pub fn nodes(&self) -> impl Iterator<Item = &CstNode> {
+        self.children.iter().filter_map(|e| match e {
+            CstElement::Node(node) => Some(node),
+            CstElement::Token(_) => None,
+        })
+    }
+
+    /// Child tokens, without nodes
+    pub fn tokens(&self) -> impl Iterator<Item = &CstToken> {
+        self.children.iter().filter_map(|e| match e {
+            CstElement::Token(token) => Some(token),
+            CstElement::Node(_) => None,
+        })
+    }
+
+    /// Quote character of a [`Rule::string`] node: `"`, `'` or `` ` ``
+    pub fn quote(&self) -> Option<char> {
+        if self.rule != Rule::string {
+            return None;
+        }
+        self.tokens()
+            .next()
+            .and_then(|token| token.text.chars().next())
+    }
+
+    /// Whether the last item of an object or array is followed by a comma
+    pub fn has_trailing_comma(&self) -> bool {
+        if self.rule != Rule::object && self.rule != Rule::array {
+            return false;
+        }
+        let mut symbols = self
+            .children
+            .iter()
+            .rev()
+            .filter(|e| !matches!(e, CstElement::Token(token) if token.is_trivia()))
+            .skip(1);
+
+        matches!(symbols.next(), Some(CstElement::Token(token)) if token.text == ",")
+    }
+}
+
+impl fmt::Display for CstNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.children {
+            match e {
+                CstElement::Node(node) => write!(f, "{}", node)?,
+                CstElement::Token(token) => write!(f, "{}", token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
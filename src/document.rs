@@ -0,0 +1,606 @@
+use crate::cst::{CstElement, CstNode};
+use crate::error::{Error, Location, SemanticErrorKind};
+use crate::parser::parse_cst;
+use crate::stringify_json::{stringify_indented, FormatOptions, Indent, KeyQuotes, Quote};
+use crate::types::{IndexMap, Path, Rule, Value};
+use crate::utils::{escape, is_identifier, unescape};
+use std::fmt;
+
+/// Text replacement: byte range and new text
+type Edit = (usize, usize, String);
+
+/// FSON document that can be edited without reformatting it:
+/// comments, whitespace and spelling of untouched values are kept,
+/// and only the edited parts of the text change. New values follow the quotes,
+/// trailing commas and layout of the text around them.
+///
+/// Paths are keys of objects and indices of arrays, values of
+/// reference declarations are entered like objects
+/// ## Example:
+/// ```
+/// let mut document = Document::parse(String::from("{\n  // HTTP\n  port: 80\n}")).unwrap();
+/// document.set(&["port"], Value::Integer(8080)).unwrap();
+/// document.to_string() // "{\n  // HTTP\n  port: 8080\n}"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    text: String,
+}
+
+impl Document {
+    pub fn parse(text: String) -> Result<Document, Error> {
+        parse_cst(text.clone())?;
+        Ok(Document { text })
+    }
+
+    /// Lossless syntax tree of the current text
+    pub fn cst(&self) -> CstNode {
+        parse_cst(self.text.clone()).unwrap()
+    }
+
+    /// Replaces value at `path`.
+    /// Missing key of an object is added, index equal to the length of an array appends to it
+    pub fn set(&mut self, path: &[&str], value: Value) -> Result<(), Error> {
+        let (last, parent_path) = match path.split_last() {
+            Some(split) => split,
+            None => {
+                let cst = self.cst();
+                let root = unwrap_declaration(cst.nodes().next().unwrap());
+                let indent = self.indent(root.span.start);
+                let text = stringify_indented(value, indent, self.newline(), &self.format(root));
+                return self.apply(vec![(root.span.start, root.span.end, text)]);
+            }
+        };
+
+        let cst = self.cst();
+        let parent = self.find(&cst, parent_path)?;
+
+        match parent.rule {
+            Rule::object => match find_pair(parent, last) {
+                Some(pair) => {
+                    let node = unwrap_declaration(pair_value(pair));
+                    let indent = self.indent(node.span.start);
+                    let text =
+                        stringify_indented(value, indent, self.newline(), &self.format(parent));
+                    self.apply(vec![(node.span.start, node.span.end, text)])
+                }
+                None => {
+                    let edits = self.insert_pair(parent, last, value);
+                    self.apply(edits)
+                }
+            },
+            Rule::array => {
+                let items: Vec<&CstNode> = parent.nodes().collect();
+                match last.parse::<usize>() {
+                    Ok(index) if index < items.len() => {
+                        let node = unwrap_declaration(items[index]);
+                        let indent = self.indent(node.span.start);
+                        let text =
+                            stringify_indented(value, indent, self.newline(), &self.format(parent));
+                        self.apply(vec![(node.span.start, node.span.end, text)])
+                    }
+                    Ok(index) if index == items.len() => {
+                        let edits = self.insert_item(parent, index, value);
+                        self.apply(edits)
+                    }
+                    _ => Err(self.not_found(parent, path)),
+                }
+            }
+            _ => Err(self.not_found(parent, path)),
+        }
+    }
+
+    /// Removes key of an object or item of an array at `path`
+    pub fn remove(&mut self, path: &[&str]) -> Result<(), Error> {
+        let cst = self.cst();
+        let (last, parent_path) = match path.split_last() {
+            Some(split) => split,
+            None => return Err(self.not_found(&cst, path)),
+        };
+        let parent = self.find(&cst, parent_path)?;
+
+        let index = match parent.rule {
+            Rule::object => find_pair(parent, last)
+                .and_then(|pair| parent.nodes().position(|e| e.span == pair.span)),
+            Rule::array => last
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index < parent.nodes().count()),
+            _ => None,
+        };
+
+        match index {
+            Some(index) => {
+                let edits = self.remove_item(parent, index);
+                self.apply(edits)
+            }
+            None => Err(self.not_found(parent, path)),
+        }
+    }
+
+    /// Inserts value into the array at `path` before `index`
+    pub fn insert_array(&mut self, path: &[&str], index: usize, value: Value) -> Result<(), Error> {
+        let cst = self.cst();
+        let array = self.find(&cst, path)?;
+
+        if array.rule != Rule::array {
+            return Err(self.not_found(array, path));
+        }
+
+        let len = array.nodes().count();
+        if index > len {
+            return Err(Error::Semantic {
+                location: Some(self.location(array)),
+                kind: SemanticErrorKind::IndexOutOfBounds { index, len },
+            });
+        }
+
+        let edits = self.insert_item(array, index, value);
+        self.apply(edits)
+    }
+
+    /// Renames key at `path`, keeping its quotes when possible
+    pub fn rename_key(&mut self, path: &[&str], key: &str) -> Result<(), Error> {
+        let cst = self.cst();
+        let (last, parent_path) = match path.split_last() {
+            Some(split) => split,
+            None => return Err(self.not_found(&cst, path)),
+        };
+        let parent = self.find(&cst, parent_path)?;
+
+        if parent.rule != Rule::object {
+            return Err(self.not_found(parent, path));
+        }
+
+        let pair = match find_pair(parent, last) {
+            Some(pair) => pair,
+            None => return Err(self.not_found(parent, path)),
+        };
+
+        if *last != key {
+            if let Some(existing) = find_pair(parent, key) {
+                return Err(Error::Semantic {
+                    location: Some(self.location(pair_key(pair))),
+                    kind: SemanticErrorKind::DuplicateKey {
+                        key: key.to_string(),
+                        first: Some(self.location(pair_key(existing))),
+                    },
+                });
+            }
+        }
+
+        let old_key = pair_key(pair);
+        let text = format_key(key, old_key);
+        self.apply(vec![(old_key.span.start, old_key.span.end, text)])
+    }
+
+    /// Node of the value at `path`
+    fn find<'a>(&self, cst: &'a CstNode, path: &[&str]) -> Result<&'a CstNode, Error> {
+        let mut node = unwrap_declaration(cst.nodes().next().unwrap());
+
+        for (i, segment) in path.iter().enumerate() {
+            let next = match node.rule {
+                Rule::object => find_pair(node, segment).map(pair_value),
+                Rule::array => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| node.nodes().nth(index)),
+                _ => None,
+            };
+
+            node = match next {
+                Some(next) => unwrap_declaration(next),
+                None => return Err(self.not_found(node, &path[..=i])),
+            };
+        }
+        Ok(node)
+    }
+
+    fn insert_pair(&self, object: &CstNode, key: &str, value: Value) -> Vec<Edit> {
+        let pairs: Vec<&CstNode> = object.nodes().collect();
+        let options = self.format(object);
+
+        let last = match pairs.last() {
+            Some(last) => *last,
+            None => {
+                let mut pair = IndexMap::new();
+                pair.insert(key.to_string(), value);
+                let indent = self.indent(object.span.start);
+                let text =
+                    stringify_indented(Value::Object(pair), indent, self.newline(), &options);
+                return vec![(object.span.start, object.span.end, text)];
+            }
+        };
+
+        let key = format_key(key, pair_key(last));
+        self.insert_after(object, pairs.len() - 1, |indent| {
+            format!(
+                "{}: {}",
+                key,
+                stringify_indented(value, indent, self.newline(), &options)
+            )
+        })
+    }
+
+    fn insert_item(&self, array: &CstNode, index: usize, value: Value) -> Vec<Edit> {
+        let items: Vec<&CstNode> = array.nodes().collect();
+        let options = self.format(array);
+
+        if items.is_empty() {
+            let indent = self.indent(array.span.start);
+            let text =
+                stringify_indented(Value::Array(vec![value]), indent, self.newline(), &options);
+            return vec![(array.span.start, array.span.end, text)];
+        }
+
+        if index == items.len() {
+            return self.insert_after(array, index - 1, |indent| {
+                stringify_indented(value, indent, self.newline(), &options)
+            });
+        }
+
+        let item = items[index];
+        let separator = self.separator(array);
+        let indent = self.indent(item.span.start);
+        let text = stringify_indented(value, indent, self.newline(), &options);
+        vec![(
+            item.span.start,
+            item.span.start,
+            format!("{},{}", text, separator),
+        )]
+    }
+
+    /// Edits that add an item after the item at `index`, usually the last one.
+    /// `item` generates the new text for an indentation
+    fn insert_after<F>(&self, container: &CstNode, index: usize, item: F) -> Vec<Edit>
+    where
+        F: FnOnce(&str) -> String,
+    {
+        let previous = container.nodes().nth(index).unwrap();
+        let separator = self.separator(container);
+        let indent = match separator.rfind('\n') {
+            Some(newline) => &separator[newline + 1..],
+            None => self.indent(previous.span.start),
+        };
+        let text = item(indent);
+
+        match self.comma_after(container, previous) {
+            Some(comma) => {
+                let position = self.line_comment_end(comma);
+                vec![(position, position, format!("{}{},", separator, text))]
+            }
+            None => {
+                let position = self.line_comment_end(previous.span.end);
+                let mut edits = vec![(position, position, format!("{}{}", separator, text))];
+                if position == previous.span.end {
+                    edits[0].2.insert(0, ',');
+                } else {
+                    edits.push((previous.span.end, previous.span.end, String::from(",")));
+                }
+                edits
+            }
+        }
+    }
+
+    /// Edits that remove the item at `index` together with its comma
+    fn remove_item(&self, container: &CstNode, index: usize) -> Vec<Edit> {
+        let items: Vec<&CstNode> = container.nodes().collect();
+        let item = items[index];
+
+        let comma = self.comma_after(container, item);
+
+        if items.len() == 1 {
+            let open = container.span.start + 1;
+            let close = container.span.end - 1;
+            let end = comma.unwrap_or(item.span.end);
+            // Without comments around the item the container becomes empty: `{}`
+            if self.text[open..item.span.start].trim().is_empty()
+                && self.text[end..close].trim().is_empty()
+            {
+                return vec![(open, close, String::new())];
+            }
+
+            // Comments are kept, the line goes only if nothing else is on it
+            let line_start = self.text[..item.span.start]
+                .rfind('\n')
+                .map(|e| e + 1)
+                .unwrap_or(0);
+            let rest = &self.text[end..];
+            let trailing = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            let line_end = end + trailing;
+            if self.text[line_start..item.span.start].trim().is_empty()
+                && self.text[line_end..].starts_with(['\r', '\n'])
+            {
+                let newline = if self.text[line_end..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+                return vec![(line_start, line_end + newline, String::new())];
+            }
+            return vec![(item.span.start, line_end, String::new())];
+        }
+
+        let mut start = item.span.start;
+        let mut end = comma.unwrap_or(item.span.end);
+        let mut edits: Vec<Edit> = vec![];
+
+        let line_start = self.text[..start].rfind('\n').map(|e| e + 1).unwrap_or(0);
+        let line_end = self.line_comment_end(end);
+        let own_line = self.text[line_start..start].trim().is_empty()
+            && self.text[line_end..].starts_with(['\r', '\n']);
+
+        if own_line {
+            start = line_start;
+            end = line_end
+                + if self.text[line_end..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+        } else if comma.is_some() {
+            end += self.text[end..].len() - self.text[end..].trim_start_matches([' ', '\t']).len();
+        }
+
+        if comma.is_none() {
+            // Last item without a trailing comma: the comma of the previous one goes too
+            let previous = items[index - 1];
+            if let Some(previous_comma) = self.comma_after(container, previous) {
+                if own_line {
+                    edits.push((previous_comma - 1, previous_comma, String::new()));
+                } else {
+                    start = previous.span.end;
+                }
+            }
+        }
+
+        edits.push((start, end, String::new()));
+        edits
+    }
+
+    /// End of the comma that follows `item`, if there is one
+    fn comma_after(&self, container: &CstNode, item: &CstNode) -> Option<usize> {
+        let mut position = container.span.start;
+        let mut after_item = false;
+
+        for e in &container.children {
+            match e {
+                CstElement::Node(node) => {
+                    if after_item {
+                        return None;
+                    }
+                    after_item = node.span == item.span;
+                    position = node.span.end;
+                }
+                CstElement::Token(token) => {
+                    position += token.text.len();
+                    if after_item && !token.is_trivia() {
+                        return if token.text == "," {
+                            Some(position)
+                        } else {
+                            None
+                        };
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Options that print new values in the style of `container`: quotes of its keys
+    /// and strings, its trailing comma and indentation, and on one line if it is on one.
+    /// What `container` does not show is taken from the rest of the document
+    fn format(&self, container: &CstNode) -> FormatOptions {
+        let cst = self.cst();
+        let mut nodes: Vec<&CstNode> = vec![container];
+        descendants(container, &mut nodes);
+        descendants(&cst, &mut nodes);
+
+        let key_quotes = match nodes.iter().find(|node| node.rule == Rule::object_pair) {
+            Some(pair) if pair_key(pair).rule == Rule::identifier => KeyQuotes::WhenNeeded,
+            _ => KeyQuotes::Always,
+        };
+
+        let quote = nodes
+            .iter()
+            .find_map(|node| node.quote().filter(|quote| *quote != '`'));
+        let quote = match quote {
+            Some('\'') => Quote::Single,
+            _ => Quote::Double,
+        };
+
+        let multiline: Vec<&CstNode> = nodes
+            .iter()
+            .copied()
+            .filter(|node| {
+                (node.rule == Rule::object || node.rule == Rule::array)
+                    && node.nodes().next().is_some()
+                    && self.text[node.span.start..node.span.end].contains('\n')
+            })
+            .collect();
+        let trailing_commas = multiline
+            .first()
+            .map(|list| list.has_trailing_comma())
+            .unwrap_or(true);
+        let indent = multiline
+            .iter()
+            .find_map(|list| {
+                let item = list.nodes().next().unwrap();
+                let nested = self.text[..item.span.start].rsplit('\n').next().unwrap();
+                if nested.starts_with('\t') {
+                    return Some(Indent::Tabs);
+                }
+                let unit = self
+                    .indent(item.span.start)
+                    .len()
+                    .checked_sub(self.indent(list.span.start).len())?;
+                (unit > 0).then_some(Indent::Spaces(unit))
+            })
+            .unwrap_or(Indent::Spaces(2));
+
+        let inline = !self.text[container.span.start..container.span.end].contains('\n');
+
+        FormatOptions {
+            indent,
+            key_quotes,
+            quote,
+            trailing_commas,
+            max_width: inline.then_some(usize::MAX),
+            ..Default::default()
+        }
+    }
+
+    /// Whitespace to put between items of the container
+    fn separator(&self, container: &CstNode) -> String {
+        let items: Vec<&CstNode> = container.nodes().collect();
+
+        if items.len() > 1 {
+            if let Some(comma) = self.comma_after(container, items[0]) {
+                let whitespace = &self.text[comma..items[1].span.start];
+                if whitespace.trim().is_empty() {
+                    return whitespace.to_string();
+                }
+                if let Some(newline) = line_break(whitespace) {
+                    return whitespace[newline..].to_string();
+                }
+            }
+        }
+
+        let before_first = &self.text[container.span.start + 1..items[0].span.start];
+        match line_break(before_first) {
+            Some(newline) => before_first[newline..].to_string(),
+            None => String::from(" "),
+        }
+    }
+
+    /// `position`, or the end of the `//` comment that follows it on the same line
+    fn line_comment_end(&self, position: usize) -> usize {
+        let rest = &self.text[position..];
+        let trimmed = rest.trim_start_matches([' ', '\t']);
+
+        if trimmed.starts_with("//") {
+            let comment_end = trimmed.find(['\r', '\n']).unwrap_or(trimmed.len());
+            position + rest.len() - trimmed.len() + comment_end
+        } else {
+            position
+        }
+    }
+
+    /// Spaces and tabs at the start of the line that contains `position`
+    fn indent(&self, position: usize) -> &str {
+        let line_start = self.text[..position]
+            .rfind('\n')
+            .map(|e| e + 1)
+            .unwrap_or(0);
+        let line = &self.text[line_start..position];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+
+    /// Line ending of the document, `\r\n` if it uses them
+    fn newline(&self) -> &'static str {
+        if self.text.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    fn apply(&mut self, mut edits: Vec<Edit>) -> Result<(), Error> {
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.0));
+
+        let mut text = self.text.clone();
+        for (start, end, replacement) in edits {
+            text.replace_range(start..end, &replacement);
+        }
+
+        parse_cst(text.clone())?;
+        self.text = text;
+        Ok(())
+    }
+
+    fn location(&self, node: &CstNode) -> Box<Location> {
+        Box::new(Location {
+            span: node.span,
+            file: None,
+            snippet: self.text[node.span.start..node.span.end].to_string(),
+        })
+    }
+
+    fn not_found(&self, node: &CstNode, path: &[&str]) -> Error {
+        Error::Semantic {
+            location: Some(self.location(node)),
+            kind: SemanticErrorKind::PathNotFound(
+                path.iter().copied().collect::<Path>().to_string(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Nodes inside of `node`, in document order
+fn descendants<'a>(node: &'a CstNode, found: &mut Vec<&'a CstNode>) {
+    for child in node.nodes() {
+        found.push(child);
+        descendants(child, found);
+    }
+}
+
+/// Value of a reference declaration, other nodes as they are
+fn unwrap_declaration(node: &CstNode) -> &CstNode {
+    if node.rule != Rule::ref_value {
+        return node;
+    }
+    let value = node
+        .nodes()
+        .find(|e| e.rule == Rule::ref_value_val)
+        .unwrap();
+    unwrap_declaration(value.nodes().next().unwrap())
+}
+
+/// Start of the last line break of `text`, `\r\n` or `\n`
+fn line_break(text: &str) -> Option<usize> {
+    let newline = text.rfind('\n')?;
+    Some(newline - text[..newline].ends_with('\r') as usize)
+}
+
+fn pair_key(pair: &CstNode) -> &CstNode {
+    pair.nodes().next().unwrap()
+}
+
+fn pair_value(pair: &CstNode) -> &CstNode {
+    pair.nodes().nth(1).unwrap()
+}
+
+/// Pair of `key`, the last one if the key is used twice like parsing does by default
+fn find_pair<'a>(object: &'a CstNode, key: &str) -> Option<&'a CstNode> {
+    object
+        .nodes()
+        .filter(|pair| key_name(pair_key(pair)) == key)
+        .last()
+}
+
+fn key_name(key: &CstNode) -> String {
+    let quote_len = key.quote().map(char::len_utf8).unwrap_or(0);
+    let text = key.to_string();
+    let text = &text[quote_len..text.len() - quote_len];
+    unescape(text).unwrap_or_else(|_| text.to_string())
+}
+
+/// Key text in the style of `like`: without quotes if both are identifiers,
+/// otherwise in the quotes of `like`
+fn format_key(key: &str, like: &CstNode) -> String {
+    if like.rule == Rule::identifier && is_identifier(key) {
+        return key.to_string();
+    }
+
+    let quote = match like.quote() {
+        Some('\'') => '\'',
+        _ => '"',
+    };
+    format!("{}{}{}", quote, escape(key, quote), quote)
+}
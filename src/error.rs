@@ -0,0 +1,474 @@
+use crate::expression::{Arity, BinaryOperator, UnaryOperator, ValueType};
+use crate::parser::{Feature, Rule};
+use crate::types::Span;
+use std::fmt;
+use std::sync::Arc;
+
+/// Place in the source where an error occurred
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub span: Span,
+    /// Name of the file, `None` for the parsed input
+    pub file: Option<Arc<String>>,
+    /// Source text covered by `span`
+    pub snippet: String,
+}
+
+impl Location {
+    pub(crate) fn from_pest(span: pest::Span<'_>) -> Location {
+        Location {
+            span: Span::from_pest(span),
+            file: None,
+            snippet: span.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.span.line, self.span.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceErrorKind {
+    /// Nothing is declared under the referenced id or path.
+    /// Holds the reference as it was written: `#id`, `#/path/to/object`
+    Unresolved(String),
+    /// References that depend on each other, the first one is repeated at the end:
+    /// `["#a", "#b", "#a"]`
+    Cycle(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncludeErrorKind {
+    /// File can not be read. `name` is the one given by the
+    /// [`SourceResolver`](crate::parser::SourceResolver), `message` is the reason
+    Load { name: String, message: String },
+    /// Files that include each other, the first one is repeated at the end:
+    /// `["a.fson", "b.fson", "a.fson"]`
+    Cycle(Vec<String>),
+    /// `#include` in input that was parsed without a
+    /// [`SourceResolver`](crate::parser::SourceResolver)
+    NoResolver,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableErrorKind {
+    /// Variable is not set and is not optional: `${env:PORT}`
+    Unset { provider: String, name: String },
+    /// No provider is registered under the name in
+    /// [`ParseOptions::providers`](crate::parser::ParseOptions::providers)
+    UnknownProvider(String),
+}
+
+/// Expression can not be evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionErrorKind {
+    /// Operator does not apply to the types of its operands
+    Operands {
+        operator: BinaryOperator,
+        left: ValueType,
+        right: ValueType,
+    },
+    /// Operator does not apply to the type of its operand
+    Operand {
+        operator: UnaryOperator,
+        operand: ValueType,
+    },
+    /// Condition of `?:` is not a bool, holds its type
+    Condition(ValueType),
+    DivisionByZero,
+    /// No function is registered under the name in
+    /// [`ParseOptions::functions`](crate::parser::ParseOptions::functions).
+    /// `suggestion` is a registered name close to it
+    UnknownFunction {
+        name: String,
+        suggestion: Option<String>,
+    },
+    /// Function is called with a number of arguments it does not take
+    Arguments {
+        function: String,
+        expected: Arity,
+        found: usize,
+    },
+    /// Argument of a function has a wrong type.
+    /// `expected` describes the types it takes: `string`, `array or object`
+    Argument {
+        function: String,
+        expected: &'static str,
+        found: ValueType,
+    },
+    /// Function registered by the host failed, holds its message
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SemanticErrorKind {
+    /// Number literal can not be represented
+    InvalidNumber(String),
+    /// Escape sequence of a string is not valid: `\ud800` without its pair
+    InvalidEscape(String),
+    /// Object key or reference is a template string with interpolations
+    InterpolatedKey,
+    /// Nothing is found at the path: `/server/port`
+    PathNotFound(String),
+    /// Path is not a JSON Pointer: `server/port`, `/a~2`
+    InvalidPath(String),
+    /// Index is past the end of an array
+    IndexOutOfBounds { index: usize, len: usize },
+    /// Key is already used in the object, `first` is where
+    DuplicateKey {
+        key: String,
+        first: Option<Box<Location>>,
+    },
+    /// Reference id is declared twice, `first` is where
+    DuplicateId {
+        id: String,
+        first: Option<Box<Location>>,
+    },
+    /// Value has no JSON representation: `NaN`, `reference`, ...
+    /// `path` is where it is: `/list/0`
+    NotRepresentable { value: String, path: String },
+    /// Feature is disallowed by [`ParseOptions`](crate::parser::ParseOptions)
+    NotAllowed(Feature),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// Input does not match the grammar
+    Syntax {
+        location: Location,
+        /// Readable names of the rules that were expected at `location`
+        expected: Vec<String>,
+    },
+
+    /// Reference can not be resolved
+    Reference {
+        location: Option<Box<Location>>,
+        kind: ReferenceErrorKind,
+    },
+
+    /// Input is well-formed but has no meaning
+    Semantic {
+        location: Option<Box<Location>>,
+        kind: SemanticErrorKind,
+    },
+
+    /// Included file can not be loaded
+    Include {
+        location: Option<Box<Location>>,
+        kind: IncludeErrorKind,
+    },
+
+    /// Variable of a template string has no value
+    Variable {
+        location: Option<Box<Location>>,
+        kind: VariableErrorKind,
+    },
+
+    /// Expression can not be evaluated
+    Expression {
+        location: Option<Box<Location>>,
+        kind: ExpressionErrorKind,
+    },
+}
+
+impl Error {
+    /// Location of the error, if it is known
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Syntax { location, .. } => Some(location),
+            Error::Reference { location, .. } => location.as_deref(),
+            Error::Semantic { location, .. } => location.as_deref(),
+            Error::Include { location, .. } => location.as_deref(),
+            Error::Variable { location, .. } => location.as_deref(),
+            Error::Expression { location, .. } => location.as_deref(),
+        }
+    }
+
+    /// Marks the location of an error found while parsing file `file` named `name`
+    pub(crate) fn in_file(mut self, file: usize, name: Option<&Arc<String>>) -> Error {
+        let location = match &mut self {
+            Error::Syntax { location, .. } => Some(location),
+            Error::Reference { location, .. }
+            | Error::Semantic { location, .. }
+            | Error::Include { location, .. }
+            | Error::Variable { location, .. }
+            | Error::Expression { location, .. } => location.as_deref_mut(),
+        };
+        if let Some(location) = location {
+            location.span.file = file;
+            location.file = name.cloned();
+        }
+        self
+    }
+
+    /// Gives a location to an error that has none
+    pub(crate) fn or_at(mut self, at: Option<Box<Location>>) -> Error {
+        match &mut self {
+            Error::Syntax { .. } => {}
+            Error::Reference { location, .. }
+            | Error::Semantic { location, .. }
+            | Error::Include { location, .. }
+            | Error::Variable { location, .. }
+            | Error::Expression { location, .. } => {
+                if location.is_none() {
+                    *location = at;
+                }
+            }
+        }
+        self
+    }
+
+    pub(crate) fn from_pest(error: pest::error::Error<Rule>, input: &str) -> Error {
+        let pos = match error.location {
+            pest::error::InputLocation::Pos(pos) => pos,
+            pest::error::InputLocation::Span((start, _)) => start,
+        };
+
+        let mut expected: Vec<String> = vec![];
+        if let pest::error::ErrorVariant::ParsingError { positives, .. } = error.variant {
+            for rule in positives {
+                let name = rule_name(rule).to_string();
+                if !expected.contains(&name) {
+                    expected.push(name);
+                }
+            }
+        }
+
+        let end = pos + token_len(&input[pos..]);
+        let (line, column) = pest::Position::new(input, pos).unwrap().line_col();
+
+        Error::Syntax {
+            location: Location {
+                span: Span {
+                    start: pos,
+                    end,
+                    line,
+                    column,
+                    file: 0,
+                },
+                file: None,
+                snippet: input[pos..end].to_string(),
+            },
+            expected,
+        }
+    }
+}
+
+/// Length of the token at the start of `input`
+fn token_len(input: &str) -> usize {
+    let word_len: usize = input
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .map(char::len_utf8)
+        .sum();
+
+    if word_len > 0 {
+        word_len
+    } else {
+        input.chars().next().map(char::len_utf8).unwrap_or(0)
+    }
+}
+
+/// Readable name of a grammar rule
+fn rule_name(rule: Rule) -> &'static str {
+    match rule {
+        Rule::identifier => "identifier",
+        Rule::colon_token => "`:`",
+        Rule::semicolon_token => "`;`",
+        Rule::comma_token => "`,`",
+        Rule::bracket_close => "`]`",
+        Rule::brace_close => "`}`",
+        Rule::paren_close => "`)`",
+        Rule::array => "array",
+        Rule::object_pair => "key",
+        Rule::object => "object",
+        Rule::string
+        | Rule::double_quotes_string
+        | Rule::single_quotes_string
+        | Rule::template_string => "string",
+        Rule::template_char => "character",
+        Rule::interpolation_template => "interpolation",
+        Rule::escape_and_unicode => "escape sequence",
+        Rule::number | Rule::int | Rule::exp | Rule::hexadecimal => "number",
+        Rule::infinity => "Infinity",
+        Rule::not_a_number => "NaN",
+        Rule::boolean => "boolean",
+        Rule::null => "null",
+        Rule::reference
+        | Rule::ref_path
+        | Rule::ref_path_index
+        | Rule::ref_parents
+        | Rule::ref_relative => "reference",
+        Rule::ref_value => "reference declaration",
+        Rule::include => "include",
+        Rule::variable | Rule::variable_optional | Rule::variable_name => "variable",
+        Rule::expression
+        | Rule::expression_value
+        | Rule::logical_or
+        | Rule::logical_and
+        | Rule::equality
+        | Rule::comparison
+        | Rule::sum
+        | Rule::product
+        | Rule::unary => "expression",
+        Rule::call | Rule::function_name => "function call",
+        Rule::or_operator
+        | Rule::and_operator
+        | Rule::equality_operator
+        | Rule::comparison_operator
+        | Rule::sum_operator
+        | Rule::product_operator
+        | Rule::unary_operator => "operator",
+        Rule::ref_value_id => "`#id`",
+        Rule::ref_value_val => "`#value`",
+        Rule::EOI => "end of input",
+        _ => "value",
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = self.location() {
+            write!(f, "{}: ", location)?;
+        }
+
+        match self {
+            Error::Syntax { location, expected } => {
+                match expected.len() {
+                    0 => write!(f, "unexpected input")?,
+                    1 => write!(f, "expected {}", expected[0])?,
+                    len => write!(
+                        f,
+                        "expected {} or {}",
+                        expected[..len - 1].join(", "),
+                        expected[len - 1]
+                    )?,
+                }
+                if location.snippet.is_empty() {
+                    write!(f, ", found end of input")
+                } else {
+                    write!(f, ", found `{}`", location.snippet)
+                }
+            }
+            Error::Reference { kind, .. } => match kind {
+                ReferenceErrorKind::Unresolved(reference) => {
+                    write!(f, "unresolved reference `{}`", reference)
+                }
+                ReferenceErrorKind::Cycle(references) => {
+                    write!(f, "reference cycle `{}`", references.join(" -> "))
+                }
+            },
+            Error::Include { kind, .. } => match kind {
+                IncludeErrorKind::Load { name, message } => {
+                    write!(f, "can not include `{}`: {}", name, message)
+                }
+                IncludeErrorKind::Cycle(files) => {
+                    write!(f, "include cycle `{}`", files.join(" -> "))
+                }
+                IncludeErrorKind::NoResolver => {
+                    write!(f, "`#include` needs a source resolver, see `parse_file`")
+                }
+            },
+            Error::Variable { kind, .. } => match kind {
+                VariableErrorKind::Unset { provider, name } => {
+                    write!(f, "variable `{}:{}` is not set", provider, name)
+                }
+                VariableErrorKind::UnknownProvider(provider) => {
+                    write!(f, "unknown value provider `{}`", provider)
+                }
+            },
+            Error::Expression { kind, .. } => match kind {
+                ExpressionErrorKind::Operands {
+                    operator,
+                    left,
+                    right,
+                } => {
+                    let verb = match operator {
+                        BinaryOperator::Add => "add",
+                        BinaryOperator::Subtract => "subtract",
+                        BinaryOperator::Multiply => "multiply",
+                        BinaryOperator::Divide => "divide",
+                        BinaryOperator::Remainder => "take the remainder of",
+                        BinaryOperator::Or | BinaryOperator::And => {
+                            return write!(
+                                f,
+                                "cannot apply `{}` to {} and {}",
+                                operator, left, right
+                            )
+                        }
+                        _ => "compare",
+                    };
+                    write!(f, "cannot {} {} and {}", verb, left, right)
+                }
+                ExpressionErrorKind::Operand { operator, operand } => match operator {
+                    UnaryOperator::Negate => write!(f, "cannot negate {}", operand),
+                    UnaryOperator::Not => write!(f, "cannot apply `!` to {}", operand),
+                },
+                ExpressionErrorKind::Condition(condition) => {
+                    write!(f, "condition must be bool, found {}", condition)
+                }
+                ExpressionErrorKind::DivisionByZero => write!(f, "division by zero"),
+                ExpressionErrorKind::UnknownFunction { name, suggestion } => {
+                    write!(f, "unknown function `{}`", name)?;
+                    match suggestion {
+                        Some(suggestion) => write!(f, ", did you mean `{}`?", suggestion),
+                        None => Ok(()),
+                    }
+                }
+                ExpressionErrorKind::Arguments {
+                    function,
+                    expected,
+                    found,
+                } => write!(f, "`{}` takes {}, found {}", function, expected, found),
+                ExpressionErrorKind::Argument {
+                    function,
+                    expected,
+                    found,
+                } => write!(f, "`{}` expects {}, found {}", function, expected, found),
+                ExpressionErrorKind::Failed(message) => write!(f, "{}", message),
+            },
+            Error::Semantic { kind, .. } => match kind {
+                SemanticErrorKind::InvalidNumber(number) => {
+                    write!(f, "invalid number `{}`", number)
+                }
+                SemanticErrorKind::InvalidEscape(escape) => {
+                    write!(f, "invalid escape sequence `{}`", escape)
+                }
+                SemanticErrorKind::InterpolatedKey => {
+                    write!(f, "interpolations are not allowed in keys and references")
+                }
+                SemanticErrorKind::PathNotFound(path) => write!(f, "nothing found at `{}`", path),
+                SemanticErrorKind::InvalidPath(path) => write!(f, "invalid path `{}`", path),
+                SemanticErrorKind::IndexOutOfBounds { index, len } => {
+                    write!(f, "index {} is out of bounds for length {}", index, len)
+                }
+                SemanticErrorKind::DuplicateKey { key, first } => {
+                    write!(f, "duplicate key `{}`", key)?;
+                    match first {
+                        Some(first) => write!(f, ", first used at {}", first),
+                        None => Ok(()),
+                    }
+                }
+                SemanticErrorKind::DuplicateId { id, first } => {
+                    write!(f, "duplicate reference id `{}`", id)?;
+                    match first {
+                        Some(first) => write!(f, ", first declared at {}", first),
+                        None => Ok(()),
+                    }
+                }
+                SemanticErrorKind::NotAllowed(feature) => write!(f, "{} are not allowed", feature),
+                SemanticErrorKind::NotRepresentable { value, path } => {
+                    write!(f, "{} at `{}` can not be represented in JSON", value, path)
+                }
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,408 @@
+use crate::error::ExpressionErrorKind;
+use crate::types::{Conversion, Value, Variable};
+use crate::utils::text;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Operator before an operand: `-x`, `!x`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+impl UnaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Not => "!",
+        }
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Operator between two operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// Sum of numbers, concatenation of strings and arrays
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl BinaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Or => "||",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+        }
+    }
+
+    /// Operators with a higher precedence are applied first: `*` before `+`, `+` before `==`.
+    /// Operators with the same precedence are applied from left to right
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 3,
+            BinaryOperator::Less
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterOrEqual => 4,
+            BinaryOperator::Add | BinaryOperator::Subtract => 5,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => 6,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Unevaluated expression of `${...}`. Operands are values,
+/// which can be references or other expressions: `#port + 1`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Unary {
+        operator: UnaryOperator,
+        operand: Value,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+    },
+    /// `condition ? then : otherwise`
+    Conditional {
+        condition: Value,
+        then: Value,
+        otherwise: Value,
+    },
+    /// `env:PORT`, `null` if it is optional and not set
+    Variable(Variable),
+    /// `upper(#name)`
+    Call {
+        function: String,
+        arguments: Vec<Value>,
+    },
+}
+
+/// Type of a value in expression errors: `cannot add string and bool`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    String,
+    /// Integers, floats, `NaN` and infinities
+    Number,
+    Bool,
+    Null,
+    Object,
+    Array,
+    /// Value that is not evaluated: reference, template string, ...
+    Other,
+}
+
+impl ValueType {
+    pub fn of(value: &Value) -> ValueType {
+        match value {
+            Value::String(_) | Value::Identifier(_) => ValueType::String,
+            Value::Number(_)
+            | Value::Integer(_)
+            | Value::BigInteger(_)
+            | Value::NaN
+            | Value::Infinity
+            | Value::NegativeInfinity => ValueType::Number,
+            Value::Boolean(_) => ValueType::Bool,
+            Value::Null => ValueType::Null,
+            Value::Object(_) => ValueType::Object,
+            Value::Array(_) => ValueType::Array,
+            _ => ValueType::Other,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValueType::String => "string",
+            ValueType::Number => "number",
+            ValueType::Bool => "bool",
+            ValueType::Null => "null",
+            ValueType::Object => "object",
+            ValueType::Array => "array",
+            ValueType::Other => "value",
+        })
+    }
+}
+
+/// Number of arguments a function takes
+/// ## Example:
+/// ```
+/// Arity::exact(1) // upper(s)
+/// Arity::range(1, 2) // round(x), round(x, digits)
+/// Arity::at_least(1) // min(x, ...)
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Arity {
+    pub min: usize,
+    /// `None` if there is no limit
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub const fn exact(count: usize) -> Arity {
+        Arity {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub const fn range(min: usize, max: usize) -> Arity {
+        Arity {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub const fn at_least(min: usize) -> Arity {
+        Arity { min, max: None }
+    }
+
+    /// Whether a call can have `count` arguments
+    pub fn allows(self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+/// `1 argument`, `1 or 2 arguments`, `at least 1 argument`
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = |count: usize| if count == 1 { "argument" } else { "arguments" };
+        let min = self.min;
+        match self.max {
+            None => write!(f, "at least {} {}", min, noun(min)),
+            Some(max) if max == min => write!(f, "{} {}", max, noun(max)),
+            Some(max) if max == min + 1 => write!(f, "{} or {} arguments", min, max),
+            Some(max) => write!(f, "{} to {} arguments", min, max),
+        }
+    }
+}
+
+/// Number operand, integers are kept exact while they fit in `i128`
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn from_value(value: &Value) -> Option<Number> {
+        match value {
+            Value::Integer(integer) => Some(Number::Integer(*integer)),
+            _ => value.as_f64().map(|float| Number::Float(float.value())),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(integer) => Value::Integer(integer)
+                .as_f64()
+                .map(Conversion::value)
+                .unwrap_or_default(),
+            Number::Float(float) => float,
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            Number::Integer(integer) => integer == 0,
+            Number::Float(float) => float == 0.0,
+        }
+    }
+}
+
+/// Number as a value, the same one the parser gives for it
+pub(crate) fn float(float: f64) -> Value {
+    if float.is_nan() {
+        Value::NaN
+    } else if float == f64::INFINITY {
+        Value::Infinity
+    } else if float == f64::NEG_INFINITY {
+        Value::NegativeInfinity
+    } else {
+        Value::Number(float)
+    }
+}
+
+/// Applies an integer operation, or the float one if an operand is
+/// a float or the result does not fit in `i128`
+fn arithmetic(
+    left: Number,
+    right: Number,
+    integer: fn(i128, i128) -> Option<i128>,
+    floats: fn(f64, f64) -> f64,
+) -> Value {
+    if let (Number::Integer(left), Number::Integer(right)) = (left, right) {
+        if let Some(result) = integer(left, right) {
+            return Value::Integer(result);
+        }
+    }
+    float(floats(left.as_f64(), right.as_f64()))
+}
+
+pub(crate) fn unary(operator: UnaryOperator, operand: Value) -> Result<Value, ExpressionErrorKind> {
+    match (operator, &operand) {
+        (UnaryOperator::Not, Value::Boolean(bool)) => Ok(Value::Boolean(!bool)),
+        (UnaryOperator::Negate, _) => match Number::from_value(&operand) {
+            Some(Number::Integer(integer)) => Ok(match integer.checked_neg() {
+                Some(integer) => Value::Integer(integer),
+                None => float(-(integer as f64)),
+            }),
+            Some(Number::Float(num)) => Ok(float(-num)),
+            None => Err(ExpressionErrorKind::Operand {
+                operator,
+                operand: ValueType::of(&operand),
+            }),
+        },
+        _ => Err(ExpressionErrorKind::Operand {
+            operator,
+            operand: ValueType::of(&operand),
+        }),
+    }
+}
+
+pub(crate) fn binary(
+    operator: BinaryOperator,
+    left: Value,
+    right: Value,
+) -> Result<Value, ExpressionErrorKind> {
+    let numbers = Number::from_value(&left).zip(Number::from_value(&right));
+    let invalid = || ExpressionErrorKind::Operands {
+        operator,
+        left: ValueType::of(&left),
+        right: ValueType::of(&right),
+    };
+
+    match operator {
+        BinaryOperator::Or | BinaryOperator::And => match (&left, &right) {
+            (Value::Boolean(left), Value::Boolean(right)) => {
+                Ok(Value::Boolean(if operator == BinaryOperator::Or {
+                    *left || *right
+                } else {
+                    *left && *right
+                }))
+            }
+            _ => Err(invalid()),
+        },
+
+        BinaryOperator::Equal => Ok(Value::Boolean(equal(&left, &right))),
+        BinaryOperator::NotEqual => Ok(Value::Boolean(!equal(&left, &right))),
+
+        BinaryOperator::Less
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterOrEqual => {
+            let ordering = match (&left, &right) {
+                (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+                _ => match numbers.ok_or_else(invalid)? {
+                    (Number::Integer(left), Number::Integer(right)) => Some(left.cmp(&right)),
+                    (left, right) => left.as_f64().partial_cmp(&right.as_f64()),
+                },
+            };
+            Ok(Value::Boolean(match ordering {
+                // `NaN` is not ordered
+                None => false,
+                Some(ordering) => match operator {
+                    BinaryOperator::Less => ordering == Ordering::Less,
+                    BinaryOperator::LessOrEqual => ordering != Ordering::Greater,
+                    BinaryOperator::Greater => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                },
+            }))
+        }
+
+        BinaryOperator::Add => match (&left, &right) {
+            (Value::String(left), Value::String(right)) => Ok(Value::String(left.clone() + right)),
+            (Value::String(str), number) if numbers_only(number) => {
+                Ok(Value::String(str.clone() + &text(number.clone(), false)))
+            }
+            (number, Value::String(str)) if numbers_only(number) => {
+                Ok(Value::String(text(number.clone(), false) + str))
+            }
+            (Value::Array(left), Value::Array(right)) => {
+                Ok(Value::Array(left.iter().chain(right).cloned().collect()))
+            }
+            _ => {
+                let (left, right) = numbers.ok_or_else(invalid)?;
+                Ok(arithmetic(left, right, i128::checked_add, |a, b| a + b))
+            }
+        },
+        BinaryOperator::Subtract => {
+            let (left, right) = numbers.ok_or_else(invalid)?;
+            Ok(arithmetic(left, right, i128::checked_sub, |a, b| a - b))
+        }
+        BinaryOperator::Multiply => {
+            let (left, right) = numbers.ok_or_else(invalid)?;
+            Ok(arithmetic(left, right, i128::checked_mul, |a, b| a * b))
+        }
+        BinaryOperator::Divide => {
+            let (left, right) = numbers.ok_or_else(invalid)?;
+            if right.is_zero() {
+                return Err(ExpressionErrorKind::DivisionByZero);
+            }
+            // Integers stay integers when they divide exactly
+            let exact = |a: i128, b: i128| match a.checked_rem(b) {
+                Some(0) => a.checked_div(b),
+                _ => None,
+            };
+            Ok(arithmetic(left, right, exact, |a, b| a / b))
+        }
+        BinaryOperator::Remainder => {
+            let (left, right) = numbers.ok_or_else(invalid)?;
+            if right.is_zero() {
+                return Err(ExpressionErrorKind::DivisionByZero);
+            }
+            Ok(arithmetic(left, right, i128::checked_rem, |a, b| a % b))
+        }
+    }
+}
+
+fn numbers_only(value: &Value) -> bool {
+    Number::from_value(value).is_some()
+}
+
+/// Equality of values, numbers are equal if they have the same value: `1 == 1.0`
+fn equal(left: &Value, right: &Value) -> bool {
+    match (Number::from_value(left), Number::from_value(right)) {
+        (Some(Number::Integer(left)), Some(Number::Integer(right))) => left == right,
+        (Some(left), Some(right)) => left.as_f64() == right.as_f64(),
+        _ => left == right,
+    }
+}
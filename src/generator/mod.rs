@@ -1,4 +1,4 @@
-use crate::stringify_json::stringify;
+use crate::stringify_json::{stringify, stringify_with, FormatOptions};
 use crate::Value;
 
 /// Generate FSON from Value
@@ -6,10 +6,28 @@ pub fn from(val: Value) -> String {
     stringify(val, 0, false)
 }
 
+/// Generate FSON from Value with options
+/// ## Example:
+/// ```
+/// generator::from_with(value, &FormatOptions { key_quotes: KeyQuotes::WhenNeeded, ..Default::default() })
+/// ```
+pub fn from_with(val: Value, options: &FormatOptions) -> String {
+    stringify_with(val, options)
+}
+
+/// Generate FSON from Value with keys of objects sorted, see [`Value::sort_keys`]
+pub fn from_sorted(val: Value) -> String {
+    let options = FormatOptions {
+        sort_keys: true,
+        ..Default::default()
+    };
+    stringify_with(val, &options)
+}
+
 #[macro_export]
 macro_rules! object {
     ($( $key: expr => $val: expr ),*) => {{
-         let mut map: std::collections::HashMap<String, Value> = std::collections::HashMap::new();
+         let mut map: $crate::IndexMap<String, $crate::Value> = $crate::IndexMap::new();
          $( map.insert($key, $val); )*
          map
     }}
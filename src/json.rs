@@ -0,0 +1,296 @@
+use crate::error::{Error, SemanticErrorKind};
+use crate::stringify_json::{stringify_expression, Indent};
+use crate::types::*;
+use crate::utils::float_literal;
+
+/// What to do with `NaN`, `Infinity` and `-Infinity`, JSON has no such numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Fail with [`SemanticErrorKind::NotRepresentable`]
+    Error,
+    /// Write `null`
+    Null,
+    /// Write `"NaN"`, `"Infinity"` or `"-Infinity"`
+    String,
+}
+
+/// What to do with references, reference declarations, template strings, includes and expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodePolicy {
+    /// Evaluate the value first, see [`Value::resolve`]
+    Resolve,
+    /// Write them as objects, the same way as the wasm bridge does:
+    /// `{ "#id": ..., "#value": ... }`, `{ "#reference_id": ... }`,
+    /// `{ "#reference_path": [...] }`, `{ "#reference_parents": 1, "#reference_path": [...] }`,
+    /// `{ "@template_string": [...] }`, `{ "#include": "file" }`, `{ "@expression": "#port + 1" }`
+    Inline,
+    /// Fail with [`SemanticErrorKind::NotRepresentable`]
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonOptions {
+    pub non_finite: NonFinitePolicy,
+    pub nodes: NodePolicy,
+    pub indent: Indent,
+    /// Everything on one line without spaces
+    pub minify: bool,
+    /// Write characters outside of ASCII as `\uXXXX` escapes
+    pub escape_unicode: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> JsonOptions {
+        JsonOptions {
+            non_finite: NonFinitePolicy::Error,
+            nodes: NodePolicy::Resolve,
+            indent: Indent::Spaces(2),
+            minify: false,
+            escape_unicode: false,
+        }
+    }
+}
+
+/// Stringify value as RFC 8259 JSON
+/// ## Example:
+/// ```
+/// let value = parse_raw(String::from("{ x: NaN, y: #/x }")).unwrap();
+/// json::stringify(value, &JsonOptions {
+///     non_finite: NonFinitePolicy::Null,
+///     minify: true,
+///     ..Default::default()
+/// }) // Ok("{\"x\":null,\"y\":null}")
+/// ```
+pub fn stringify(val: Value, options: &JsonOptions) -> Result<String, Error> {
+    let val = match options.nodes {
+        NodePolicy::Resolve => val.resolve()?,
+        _ => val,
+    };
+
+    let writer = Writer { options };
+    let mut str = String::new();
+    writer.write(&val, &mut str, "", &mut vec![])?;
+    Ok(str)
+}
+
+struct Writer<'a> {
+    options: &'a JsonOptions,
+}
+
+impl Writer<'_> {
+    /// `path` is where the value is in the document, for errors
+    fn write(
+        &self,
+        value: &Value,
+        str: &mut String,
+        indent: &str,
+        path: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        match value {
+            Value::Null => *str += "null",
+            Value::Boolean(bool) => *str += &bool.to_string(),
+            Value::Number(num) if num.is_nan() => self.non_finite("NaN", str, path)?,
+            Value::Number(num) if num.is_infinite() => {
+                let name = if *num > 0.0 { "Infinity" } else { "-Infinity" };
+                self.non_finite(name, str, path)?
+            }
+            Value::Number(num) => *str += &float_literal(*num),
+            Value::Integer(integer) => *str += &integer.to_string(),
+            Value::BigInteger(integer) => *str += integer,
+            Value::String(s) | Value::Identifier(s) => self.string(s, str),
+            Value::NaN => self.non_finite("NaN", str, path)?,
+            Value::Infinity => self.non_finite("Infinity", str, path)?,
+            Value::NegativeInfinity => self.non_finite("-Infinity", str, path)?,
+
+            Value::Object(object) => {
+                let pairs: Vec<(String, &Value)> =
+                    object.iter().map(|(k, v)| (k.clone(), v)).collect();
+                self.object(&pairs, str, indent, path)?;
+            }
+
+            Value::Array(arr) => {
+                if arr.is_empty() {
+                    *str += "[]";
+                    return Ok(());
+                }
+                let inner = self.nested(indent);
+                *str += "[";
+                for (i, e) in arr.iter().enumerate() {
+                    if i > 0 {
+                        *str += ",";
+                    }
+                    self.newline(str, &inner);
+                    path.push(i.to_string());
+                    self.write(e, str, &inner, path)?;
+                    path.pop();
+                }
+                self.newline(str, indent);
+                *str += "]";
+            }
+
+            Value::TemplateString(parts) => {
+                self.node("template string", path)?;
+                let parts: Vec<Value> = parts
+                    .iter()
+                    .map(|part| match part {
+                        TemplateValue::String(s) => Value::String(s.clone()),
+                        TemplateValue::Interpolation(value) => value.clone(),
+                        TemplateValue::Variable(variable) => Value::Object(IndexMap::from([(
+                            String::from("#variable"),
+                            Value::String(variable.to_string()),
+                        )])),
+                    })
+                    .collect();
+                let parts = Value::Array(parts);
+                self.object(
+                    &[("@template_string".to_string(), &parts)],
+                    str,
+                    indent,
+                    path,
+                )?;
+            }
+
+            Value::ReferenceDeclaration { id, value } => {
+                self.node("reference declaration", path)?;
+                let id = Value::String(id.clone());
+                let pairs = [("#id".to_string(), &id), ("#value".to_string(), &**value)];
+                self.object(&pairs, str, indent, path)?;
+            }
+
+            Value::Include(name) => {
+                self.node("include", path)?;
+                let name = Value::String(name.clone());
+                self.object(&[("#include".to_string(), &name)], str, indent, path)?;
+            }
+
+            Value::Expression(expression) => {
+                self.node("expression", path)?;
+                let text = Value::String(stringify_expression(expression));
+                self.object(&[("@expression".to_string(), &text)], str, indent, path)?;
+            }
+
+            Value::Reference(reference) => {
+                self.node("reference", path)?;
+                let segments = |path: &Path| {
+                    Value::Array(path.segments().iter().cloned().map(Value::String).collect())
+                };
+                let pairs = match reference {
+                    ReferenceAsValue::Id(id) => {
+                        vec![("#reference_id".to_string(), Value::String(id.clone()))]
+                    }
+                    ReferenceAsValue::Path(parts) => {
+                        vec![("#reference_path".to_string(), segments(parts))]
+                    }
+                    ReferenceAsValue::Relative {
+                        parents,
+                        path: parts,
+                    } => vec![
+                        (
+                            "#reference_parents".to_string(),
+                            Value::Integer(*parents as i128),
+                        ),
+                        ("#reference_path".to_string(), segments(parts)),
+                    ],
+                };
+                let pairs: Vec<(String, &Value)> =
+                    pairs.iter().map(|(k, v)| (k.clone(), v)).collect();
+                self.object(&pairs, str, indent, path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn object(
+        &self,
+        pairs: &[(String, &Value)],
+        str: &mut String,
+        indent: &str,
+        path: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        if pairs.is_empty() {
+            *str += "{}";
+            return Ok(());
+        }
+
+        let inner = self.nested(indent);
+        *str += "{";
+        for (i, (key, value)) in pairs.iter().enumerate() {
+            if i > 0 {
+                *str += ",";
+            }
+            self.newline(str, &inner);
+            self.string(key, str);
+            *str += if self.options.minify { ":" } else { ": " };
+            path.push(key.clone());
+            self.write(value, str, &inner, path)?;
+            path.pop();
+        }
+        self.newline(str, indent);
+        *str += "}";
+        Ok(())
+    }
+
+    fn non_finite(&self, name: &str, str: &mut String, path: &[String]) -> Result<(), Error> {
+        match self.options.non_finite {
+            NonFinitePolicy::Error => return Err(not_representable(name, path)),
+            NonFinitePolicy::Null => *str += "null",
+            NonFinitePolicy::String => self.string(name, str),
+        }
+        Ok(())
+    }
+
+    /// Checks that references, declarations and template strings can be inlined
+    fn node(&self, name: &str, path: &[String]) -> Result<(), Error> {
+        match self.options.nodes {
+            NodePolicy::Error => Err(not_representable(name, path)),
+            _ => Ok(()),
+        }
+    }
+
+    fn string(&self, s: &str, str: &mut String) {
+        str.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => *str += "\\\"",
+                '\\' => *str += "\\\\",
+                '\n' => *str += "\\n",
+                '\r' => *str += "\\r",
+                '\t' => *str += "\\t",
+                '\u{8}' => *str += "\\b",
+                '\u{c}' => *str += "\\f",
+                c if c < ' ' || (self.options.escape_unicode && !c.is_ascii()) => {
+                    let mut units = [0; 2];
+                    for unit in c.encode_utf16(&mut units) {
+                        *str += &format!("\\u{:04x}", unit);
+                    }
+                }
+                c => str.push(c),
+            }
+        }
+        str.push('"');
+    }
+
+    fn nested(&self, indent: &str) -> String {
+        match self.options.indent {
+            Indent::Spaces(width) => format!("{}{}", indent, " ".repeat(width)),
+            Indent::Tabs => format!("{}\t", indent),
+        }
+    }
+
+    fn newline(&self, str: &mut String, indent: &str) {
+        if !self.options.minify {
+            *str += "\n";
+            *str += indent;
+        }
+    }
+}
+
+fn not_representable(value: &str, path: &[String]) -> Error {
+    Error::Semantic {
+        location: None,
+        kind: SemanticErrorKind::NotRepresentable {
+            value: value.to_string(),
+            path: path.iter().collect::<Path>().to_string(),
+        },
+    }
+}
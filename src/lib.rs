@@ -4,14 +4,23 @@ extern crate pest;
 #[macro_use]
 extern crate pest_derive;
 
+pub mod ast;
+pub mod cst;
+pub mod document;
+pub mod error;
+pub mod expression;
 pub mod generator;
+pub mod json;
 pub mod parser;
+mod resolver;
 
 pub mod stringify_json;
 
 pub mod types;
 pub mod utils;
 
+pub use error::*;
+pub use expression::*;
 pub use types::*;
 pub use utils::*;
 
@@ -0,0 +1,122 @@
+use crate::cst::{CstElement, CstNode, CstToken, TokenKind};
+use crate::types::*;
+
+pub fn to_cst(pairs: Pairs<'_, Rule>, input: &str) -> CstNode {
+    let mut children: Vec<CstElement> = vec![];
+    let mut pos = 0;
+
+    for pair in pairs {
+        if pair.as_rule() == Rule::EOI {
+            continue;
+        }
+        let span = pair.as_span();
+        lex(&input[pos..span.start()], &mut children);
+        children.push(CstElement::Node(build(pair, input)));
+        pos = span.end();
+    }
+    lex(&input[pos..], &mut children);
+
+    CstNode {
+        rule: Rule::document,
+        span: Span::from_pest(pest::Span::new(input, 0, input.len()).unwrap()),
+        children,
+    }
+}
+
+/// Rules whose text is kept as a single token
+fn is_literal(rule: Rule) -> bool {
+    matches!(
+        rule,
+        Rule::identifier
+            | Rule::ref_path_index
+            | Rule::ref_parents
+            | Rule::double_quotes_string
+            | Rule::single_quotes_string
+            | Rule::template_char
+            | Rule::variable_name
+            | Rule::variable_optional
+            | Rule::function_name
+            | Rule::or_operator
+            | Rule::and_operator
+            | Rule::equality_operator
+            | Rule::comparison_operator
+            | Rule::sum_operator
+            | Rule::product_operator
+            | Rule::unary_operator
+            | Rule::number
+            | Rule::not_a_number
+            | Rule::boolean
+            | Rule::null
+    )
+}
+
+fn build(pair: Pair<'_, Rule>, input: &str) -> CstNode {
+    let rule = pair.as_rule();
+    let span = pair.as_span();
+    let mut children: Vec<CstElement> = vec![];
+
+    if is_literal(rule) {
+        children.push(CstElement::Token(CstToken {
+            kind: TokenKind::Text,
+            text: span.as_str().to_string(),
+        }));
+    } else {
+        let mut pos = span.start();
+        for inner in pair.into_inner() {
+            let inner_span = inner.as_span();
+            lex(&input[pos..inner_span.start()], &mut children);
+            children.push(CstElement::Node(build(inner, input)));
+            pos = inner_span.end();
+        }
+        lex(&input[pos..span.end()], &mut children);
+    }
+
+    CstNode {
+        rule,
+        span: Span::from_pest(span),
+        children,
+    }
+}
+
+/// Splits text between nodes into whitespace, comments and symbols
+fn lex(mut text: &str, children: &mut Vec<CstElement>) {
+    while !text.is_empty() {
+        let (kind, len) = if text.starts_with("//") {
+            (
+                TokenKind::Comment,
+                text.find(['\r', '\n']).unwrap_or(text.len()),
+            )
+        } else if text.starts_with("/*") {
+            (
+                TokenKind::Comment,
+                text.find("*/").map(|e| e + 2).unwrap_or(text.len()),
+            )
+        } else if text.starts_with(is_whitespace) {
+            (
+                TokenKind::Whitespace,
+                text.find(|c| !is_whitespace(c)).unwrap_or(text.len()),
+            )
+        } else if text.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            (
+                TokenKind::Symbol,
+                text.find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(text.len()),
+            )
+        } else if text.starts_with("${") {
+            (TokenKind::Symbol, 2)
+        } else {
+            (TokenKind::Symbol, text.chars().next().unwrap().len_utf8())
+        };
+
+        children.push(CstElement::Token(CstToken {
+            kind,
+            text: text[..len].to_string(),
+        }));
+        text = &text[len..];
+    }
+}
+
+/// Characters of the `WHITESPACE` rule
+fn is_whitespace(c: char) -> bool {
+    (c.is_whitespace() && c != '\u{85}') || c == '\u{feff}'
+}
@@ -0,0 +1,144 @@
+use crate::cst::{CstElement, CstNode, CstToken, TokenKind};
+use crate::error::{Error, Location, SemanticErrorKind};
+use crate::parser::{Feature, ParseOptions};
+use crate::types::*;
+
+/// Fails on the first feature of the document that `options` disallow
+pub fn check(cst: &CstNode, input: &str, options: &ParseOptions) -> Result<(), Error> {
+    Checker { input, options }.node(cst, Rule::document)
+}
+
+struct Checker<'a> {
+    input: &'a str,
+    options: &'a ParseOptions,
+}
+
+impl Checker<'_> {
+    fn node(&self, node: &CstNode, parent: Rule) -> Result<(), Error> {
+        self.rule(node, parent)?;
+
+        let mut pos = node.span.start;
+        let mut last_comma: Option<usize> = None;
+        for e in &node.children {
+            match e {
+                CstElement::Node(child) => {
+                    self.node(child, node.rule)?;
+                    pos = child.span.end;
+                }
+                CstElement::Token(token) => {
+                    self.token(token, pos)?;
+                    if token.kind == TokenKind::Symbol && token.text == "," {
+                        last_comma = Some(pos);
+                    }
+                    pos += token.text.len();
+                }
+            }
+        }
+
+        if node.has_trailing_comma() {
+            let comma = last_comma.unwrap();
+            self.allow(Feature::TrailingCommas, comma, comma + 1)?;
+        }
+        Ok(())
+    }
+
+    fn rule(&self, node: &CstNode, parent: Rule) -> Result<(), Error> {
+        let (start, end) = (node.span.start, node.span.end);
+
+        match node.rule {
+            Rule::identifier if parent == Rule::object_pair => {
+                self.allow(Feature::UnquotedKeys, start, end)
+            }
+            Rule::string => match node.quote() {
+                Some('\'') => self.allow(Feature::SingleQuotes, start, end),
+                Some('`') => self.allow(Feature::TemplateStrings, start, end),
+                _ => Ok(()),
+            },
+            Rule::double_quotes_string | Rule::single_quotes_string | Rule::template_char => {
+                self.string(&node.to_string(), start)
+            }
+            Rule::number => self.number(&node.to_string(), start, end),
+            Rule::not_a_number => self.allow(Feature::NonFiniteNumbers, start, end),
+            Rule::reference | Rule::ref_value => self.allow(Feature::References, start, end),
+            Rule::include => self.allow(Feature::Includes, start, end),
+            Rule::expression_value => self.allow(Feature::Expressions, start, end),
+            _ => Ok(()),
+        }
+    }
+
+    fn token(&self, token: &CstToken, pos: usize) -> Result<(), Error> {
+        match token.kind {
+            TokenKind::Comment => self.allow(Feature::Comments, pos, pos + token.text.len()),
+            TokenKind::Whitespace => {
+                match token
+                    .text
+                    .char_indices()
+                    .find(|(_, c)| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+                {
+                    Some((i, c)) => self.allow(
+                        Feature::WhitespaceExtensions,
+                        pos + i,
+                        pos + i + c.len_utf8(),
+                    ),
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Characters of a string literal without quotes
+    fn string(&self, text: &str, start: usize) -> Result<(), Error> {
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            let pos = start + i;
+            match c {
+                '\\' => {
+                    let (_, escape) = chars.next().unwrap();
+                    // `\r\n` after a backslash is one line continuation
+                    if escape == '\r' && chars.peek().map(|(_, c)| *c) == Some('\n') {
+                        chars.next();
+                    }
+                    if !matches!(escape, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u') {
+                        let end = pos + 1 + escape.len_utf8();
+                        self.allow(Feature::StringExtensions, pos, end)?;
+                    }
+                }
+                '\r' | '\n' => self.allow(Feature::MultilineStrings, pos, pos + 1)?,
+                c if c < ' ' => self.allow(Feature::StringExtensions, pos, pos + 1)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn number(&self, text: &str, start: usize, end: usize) -> Result<(), Error> {
+        if text.ends_with("Infinity") {
+            self.allow(Feature::NonFiniteNumbers, start, end)?;
+        }
+
+        let unsigned = text.trim_start_matches('-');
+        let extended = unsigned.starts_with(['+', '.'])
+            || unsigned.contains(['x', 'X'])
+            || unsigned.ends_with('.')
+            || unsigned.contains(".e")
+            || unsigned.contains(".E");
+        if extended {
+            self.allow(Feature::NumberExtensions, start, end)?;
+        }
+        Ok(())
+    }
+
+    fn allow(&self, feature: Feature, start: usize, end: usize) -> Result<(), Error> {
+        if self.options.allows(feature) {
+            return Ok(());
+        }
+        Err(Error::Semantic {
+            location: Some(Box::new(Location::from_pest(
+                pest::Span::new(self.input, start, end).unwrap(),
+            ))),
+            kind: SemanticErrorKind::NotAllowed(feature),
+        })
+    }
+}
@@ -0,0 +1,431 @@
+use crate::error::{Error, ExpressionErrorKind};
+use crate::expression::{self, Arity, BinaryOperator, ValueType};
+use crate::json::{self, JsonOptions, NodePolicy, NonFinitePolicy};
+use crate::types::*;
+use crate::utils::text;
+use std::fmt;
+use std::sync::Arc;
+
+/// Function that can be called in expressions: `upper(#name)`
+struct Builtin {
+    name: &'static str,
+    arity: Arity,
+    call: fn(&Arguments) -> Result<Value, ExpressionErrorKind>,
+}
+
+const BUILTINS: &[Builtin] = &[
+    // Strings
+    builtin("upper", Arity::exact(1), |args| {
+        Ok(Value::String(args.string(0)?.to_uppercase()))
+    }),
+    builtin("lower", Arity::exact(1), |args| {
+        Ok(Value::String(args.string(0)?.to_lowercase()))
+    }),
+    builtin("trim", Arity::exact(1), |args| {
+        Ok(Value::String(args.string(0)?.trim().to_string()))
+    }),
+    builtin("replace", Arity::exact(3), |args| {
+        let (str, from, to) = (args.string(0)?, args.string(1)?, args.string(2)?);
+        Ok(Value::String(str.replace(from, to)))
+    }),
+    builtin("split", Arity::exact(2), split),
+    builtin("join", Arity::exact(2), |args| {
+        let items: Vec<String> = args
+            .array(0)?
+            .iter()
+            .map(|item| text(item.clone(), true))
+            .collect();
+        Ok(Value::String(items.join(args.string(1)?)))
+    }),
+    // Arrays and objects
+    builtin("len", Arity::exact(1), |args| {
+        let len = match args.get(0) {
+            Value::String(str) | Value::Identifier(str) => str.chars().count(),
+            Value::Array(arr) => arr.len(),
+            Value::Object(object) => object.len(),
+            _ => return Err(args.invalid(0, "string, array or object")),
+        };
+        Ok(Value::Integer(len as i128))
+    }),
+    builtin("keys", Arity::exact(1), |args| {
+        let keys = args.object(0)?.keys().cloned().map(Value::String);
+        Ok(Value::Array(keys.collect()))
+    }),
+    builtin("values", Arity::exact(1), |args| {
+        Ok(Value::Array(args.object(0)?.values().cloned().collect()))
+    }),
+    builtin("contains", Arity::exact(2), contains),
+    // Numbers
+    builtin("min", Arity::at_least(1), |args| {
+        extreme(args, BinaryOperator::Less)
+    }),
+    builtin("max", Arity::at_least(1), |args| {
+        extreme(args, BinaryOperator::Greater)
+    }),
+    builtin("round", Arity::range(1, 2), round),
+    builtin("number", Arity::exact(1), number),
+    // Others
+    builtin("default", Arity::exact(2), |args| {
+        Ok(match args.get(0) {
+            Value::Null => args.get(1).clone(),
+            value => value.clone(),
+        })
+    }),
+    builtin("base64", Arity::exact(1), |args| {
+        Ok(Value::String(base64(args.string(0)?.as_bytes())))
+    }),
+    builtin("json", Arity::exact(1), |args| {
+        let options = JsonOptions {
+            non_finite: NonFinitePolicy::Null,
+            nodes: NodePolicy::Inline,
+            minify: true,
+            ..Default::default()
+        };
+        // Only non-finite numbers and unevaluated values can fail, the options allow both
+        let json = json::stringify(args.get(0).clone(), &options).unwrap_or_default();
+        Ok(Value::String(json))
+    }),
+];
+
+const fn builtin(
+    name: &'static str,
+    arity: Arity,
+    call: fn(&Arguments) -> Result<Value, ExpressionErrorKind>,
+) -> Builtin {
+    Builtin { name, arity, call }
+}
+
+/// Function that the host registers, called with the evaluated arguments
+type HostFunction = dyn Fn(&[Value]) -> Result<Value, Error> + Send + Sync;
+
+#[derive(Clone)]
+enum Function {
+    Builtin(&'static Builtin),
+    Host {
+        arity: Arity,
+        call: Arc<HostFunction>,
+    },
+}
+
+/// Functions that expressions can call, by name.
+/// The default registry has the built-in ones: `upper`, `len`, `max`, ...
+/// ## Example:
+/// ```
+/// let mut options = ParseOptions::default();
+/// options.functions.insert("region_endpoint", Arity::exact(1), |args| match &args[0] {
+///     Value::String(region) => Ok(Value::String(format!("https://{}.example.com", region))),
+///     _ => Err(Error::Expression {
+///         location: None,
+///         kind: ExpressionErrorKind::Failed(String::from("region must be a string")),
+///     }),
+/// });
+/// parse_with(String::from("`${region_endpoint('eu')}/api`"), &options)
+/// ```
+#[derive(Clone)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Function>,
+}
+
+impl FunctionRegistry {
+    /// No functions, not even the built-in ones
+    pub fn empty() -> FunctionRegistry {
+        FunctionRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Registers `function` as `name`, replacing the one that had the name.
+    /// Calls with a number of arguments that `arity` does not allow fail before it is called
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        arity: Arity,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    ) {
+        self.functions.insert(
+            name.into(),
+            Function::Host {
+                arity,
+                call: Arc::new(function),
+            },
+        );
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.functions.remove(name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Calls the function `name`. Errors have no location, unless the function gave one
+    pub(crate) fn call(&self, name: &str, arguments: &[Value]) -> Result<Value, Error> {
+        let error = |kind| Error::Expression {
+            location: None,
+            kind,
+        };
+        let function = self.functions.get(name).ok_or_else(|| {
+            error(ExpressionErrorKind::UnknownFunction {
+                name: name.to_string(),
+                suggestion: self.suggestion(name),
+            })
+        })?;
+
+        let arity = match function {
+            Function::Builtin(builtin) => builtin.arity,
+            Function::Host { arity, .. } => *arity,
+        };
+        if !arity.allows(arguments.len()) {
+            return Err(error(ExpressionErrorKind::Arguments {
+                function: name.to_string(),
+                expected: arity,
+                found: arguments.len(),
+            }));
+        }
+
+        match function {
+            Function::Builtin(builtin) => (builtin.call)(&Arguments {
+                function: name,
+                values: arguments,
+            })
+            .map_err(error),
+            Function::Host { call, .. } => call(arguments),
+        }
+    }
+
+    /// Registered name closest to `name`, if it differs in about a third of the characters
+    fn suggestion(&self, name: &str) -> Option<String> {
+        let limit = (name.chars().count() / 3).max(1);
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|(distance, _)| *distance <= limit)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate.clone())
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> FunctionRegistry {
+        FunctionRegistry {
+            functions: BUILTINS
+                .iter()
+                .map(|builtin| (builtin.name.to_string(), Function::Builtin(builtin)))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+/// Levenshtein distance in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Evaluated arguments of a built-in call. Accessors fail with the type the function expects
+struct Arguments<'a> {
+    function: &'a str,
+    values: &'a [Value],
+}
+
+impl Arguments<'_> {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn get(&self, index: usize) -> &Value {
+        &self.values[index]
+    }
+
+    fn string(&self, index: usize) -> Result<&str, ExpressionErrorKind> {
+        match self.get(index) {
+            Value::String(str) | Value::Identifier(str) => Ok(str),
+            _ => Err(self.invalid(index, "string")),
+        }
+    }
+
+    fn array(&self, index: usize) -> Result<&[Value], ExpressionErrorKind> {
+        match self.get(index) {
+            Value::Array(arr) => Ok(arr),
+            _ => Err(self.invalid(index, "array")),
+        }
+    }
+
+    fn object(&self, index: usize) -> Result<&IndexMap<String, Value>, ExpressionErrorKind> {
+        match self.get(index) {
+            Value::Object(object) => Ok(object),
+            _ => Err(self.invalid(index, "object")),
+        }
+    }
+
+    fn number(&self, index: usize) -> Result<f64, ExpressionErrorKind> {
+        self.get(index)
+            .as_f64()
+            .map(Conversion::value)
+            .ok_or_else(|| self.invalid(index, "number"))
+    }
+
+    /// Error for the argument at `index` that is not one of `expected`
+    fn invalid(&self, index: usize, expected: &'static str) -> ExpressionErrorKind {
+        ExpressionErrorKind::Argument {
+            function: self.function.to_string(),
+            expected,
+            found: ValueType::of(self.get(index)),
+        }
+    }
+}
+
+/// `split(str, separator)`, an empty separator splits into characters
+fn split(args: &Arguments) -> Result<Value, ExpressionErrorKind> {
+    let (str, separator) = (args.string(0)?, args.string(1)?);
+    let parts: Vec<Value> = if separator.is_empty() {
+        str.chars().map(|c| Value::String(c.to_string())).collect()
+    } else {
+        str.split(separator)
+            .map(|part| Value::String(part.to_string()))
+            .collect()
+    };
+    Ok(Value::Array(parts))
+}
+
+/// `contains(string, substring)`, `contains(array, item)` or `contains(object, key)`
+fn contains(args: &Arguments) -> Result<Value, ExpressionErrorKind> {
+    let found = match args.get(0) {
+        Value::String(str) | Value::Identifier(str) => str.contains(args.string(1)?),
+        Value::Array(arr) => arr.iter().any(|item| {
+            expression::binary(BinaryOperator::Equal, item.clone(), args.get(1).clone())
+                == Ok(Value::Boolean(true))
+        }),
+        Value::Object(object) => object.contains_key(args.string(1)?),
+        _ => return Err(args.invalid(0, "string, array or object")),
+    };
+    Ok(Value::Boolean(found))
+}
+
+/// Smallest or largest of the arguments, or of the items of the only argument
+/// if it is an array. Values are compared as with `operator`
+fn extreme(args: &Arguments, operator: BinaryOperator) -> Result<Value, ExpressionErrorKind> {
+    let values = match (args.len(), args.get(0)) {
+        (1, Value::Array(arr)) => arr.as_slice(),
+        _ => args.values,
+    };
+
+    if values.is_empty() {
+        return Err(args.invalid(0, "non-empty array"));
+    }
+
+    let mut result: Option<&Value> = None;
+    for value in values {
+        result = match result {
+            Some(current) => match expression::binary(operator, value.clone(), current.clone())? {
+                Value::Boolean(true) => Some(value),
+                _ => Some(current),
+            },
+            None => Some(value),
+        };
+    }
+    Ok(result.cloned().unwrap_or(Value::Null))
+}
+
+/// `round(x)` is an integer, `round(x, digits)` keeps `digits` digits after the point
+fn round(args: &Arguments) -> Result<Value, ExpressionErrorKind> {
+    let number = args.number(0)?;
+    let digits = match args.values.get(1) {
+        None => 0,
+        Some(Value::Integer(digits)) => *digits,
+        Some(_) => return Err(args.invalid(1, "integer")),
+    };
+
+    if let Value::Integer(integer) = args.get(0) {
+        if digits >= 0 {
+            return Ok(args.get(0).clone());
+        }
+        // Integers stay integers: `round(1250, -2)` is `1300`
+        let factor = u32::try_from(digits.unsigned_abs())
+            .ok()
+            .and_then(|exponent| 10i128.checked_pow(exponent));
+        let rounded = match factor {
+            Some(factor) => {
+                let remainder = integer % factor;
+                let down = integer - remainder;
+                if remainder.abs() >= factor - remainder.abs() {
+                    down.checked_add(factor * remainder.signum())
+                } else {
+                    Some(down)
+                }
+            }
+            None => Some(0),
+        };
+        if let Some(rounded) = rounded {
+            return Ok(Value::Integer(rounded));
+        }
+    }
+    if digits == 0 {
+        let rounded = number.round();
+        if rounded.is_finite() && rounded.abs() < i128::MAX as f64 {
+            return Ok(Value::Integer(rounded as i128));
+        }
+        return Ok(expression::float(rounded));
+    }
+
+    let factor = 10f64.powi(digits.clamp(-308, 308) as i32);
+    Ok(expression::float((number * factor).round() / factor))
+}
+
+/// `number('8080')` is `8080`, numbers are kept as they are
+fn number(args: &Arguments) -> Result<Value, ExpressionErrorKind> {
+    let str = match args.get(0) {
+        Value::String(str) => str.trim(),
+        value if value.as_f64().is_some() => return Ok(value.clone()),
+        _ => return Err(args.invalid(0, "number or numeric string")),
+    };
+
+    if let Ok(integer) = str.parse::<i128>() {
+        return Ok(Value::Integer(integer));
+    }
+    match str.parse::<f64>() {
+        Ok(float) if float.is_finite() => Ok(Value::Number(float)),
+        _ => Err(args.invalid(0, "number or numeric string")),
+    }
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut str = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                str.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                str.push('=');
+            }
+        }
+    }
+    str
+}
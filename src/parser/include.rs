@@ -0,0 +1,330 @@
+use crate::ast::{Node, Spanned, TemplateNode};
+use crate::error::{Error, IncludeErrorKind, Location};
+use crate::parser::{parse_node, ParseOptions};
+use crate::types::{HashMap, Span};
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+use std::{fs, io};
+
+/// Finds and reads the files of `#include`
+pub trait SourceResolver {
+    /// Name of the file included as `path` from the file named `from`.
+    /// `from` is `None` for the file passed to [`parse_file`](crate::parser::parse_file).
+    /// Files with the same name are the same file
+    fn resolve(&self, path: &str, from: Option<&str>) -> String;
+
+    /// Text of the file named `name`
+    fn load(&self, name: &str) -> io::Result<String>;
+}
+
+/// Files on disk. Paths are relative to the directory of the including file,
+/// the path given to [`parse_file`](crate::parser::parse_file) is relative to `base`.
+/// Files outside of `base` can not be loaded
+/// ## Example:
+/// ```
+/// parse_file("config.fson", &FileResolver::new("/etc/app"), &ParseOptions::default())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileResolver {
+    base: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(base: impl Into<PathBuf>) -> FileResolver {
+        FileResolver { base: base.into() }
+    }
+}
+
+impl SourceResolver for FileResolver {
+    fn resolve(&self, path: &str, from: Option<&str>) -> String {
+        let dir = match from {
+            Some(from) => std::path::Path::new(from)
+                .parent()
+                .map(|dir| dir.to_path_buf())
+                .unwrap_or_default(),
+            None => self.base.clone(),
+        };
+        let path = dir.join(path);
+        fs::canonicalize(&path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn load(&self, name: &str) -> io::Result<String> {
+        let base = fs::canonicalize(&self.base)?;
+        let path = fs::canonicalize(name)?;
+        if !path.starts_with(&base) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("`{}` is outside of `{}`", name, base.display()),
+            ));
+        }
+        fs::read_to_string(path)
+    }
+}
+
+/// Files kept in memory by name. Paths are relative to the including file
+/// and use `/` as the separator
+/// ## Example:
+/// ```
+/// let mut files = MapResolver::new();
+/// files.insert("main.fson", "{ db: #include 'db/main.fson' }");
+/// files.insert("db/main.fson", "{ host: 'localhost', port: #include './port.fson' }");
+/// files.insert("db/port.fson", "5432");
+/// parse_file("main.fson", &files, &ParseOptions::default())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MapResolver {
+    files: HashMap<String, String>,
+}
+
+impl MapResolver {
+    pub fn new() -> MapResolver {
+        MapResolver::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, text: impl Into<String>) {
+        self.files.insert(name.into(), text.into());
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for MapResolver {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> MapResolver {
+        MapResolver {
+            files: iter
+                .into_iter()
+                .map(|(name, text)| (name.into(), text.into()))
+                .collect(),
+        }
+    }
+}
+
+impl SourceResolver for MapResolver {
+    fn resolve(&self, path: &str, from: Option<&str>) -> String {
+        let joined = match from.and_then(|from| from.rsplit_once('/')) {
+            Some((dir, _)) if !path.starts_with('/') => PathBuf::from(dir).join(path),
+            _ => PathBuf::from(path),
+        };
+
+        let mut segments: Vec<String> = vec![];
+        for component in joined.components() {
+            match component {
+                Component::Normal(segment) => segments.push(segment.to_string_lossy().into()),
+                Component::ParentDir => {
+                    segments.pop();
+                }
+                _ => {}
+            }
+        }
+        segments.join("/")
+    }
+
+    fn load(&self, name: &str) -> io::Result<String> {
+        self.files.get(name).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no file named `{}`", name))
+        })
+    }
+}
+
+/// Parsed input or included file
+pub(crate) struct Source {
+    /// `None` for input that was not loaded with a [`SourceResolver`],
+    /// shared with the locations of errors
+    pub name: Option<Arc<String>>,
+    pub text: String,
+}
+
+/// Parses files and replaces their `#include`s with the included trees.
+/// Nodes keep the index of their file in `sources` in their spans
+pub(crate) struct Loader<'a> {
+    resolver: Option<&'a dyn SourceResolver>,
+    options: &'a ParseOptions,
+    pub sources: Vec<Source>,
+    /// Files that are being included, for cycle errors
+    stack: Vec<usize>,
+    /// Trees of included files by name, a file included twice is parsed once
+    /// and its nodes keep the same spans
+    included: HashMap<String, Spanned<Node>>,
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(resolver: Option<&'a dyn SourceResolver>, options: &'a ParseOptions) -> Loader<'a> {
+        Loader {
+            resolver,
+            options,
+            sources: vec![],
+            stack: vec![],
+            included: HashMap::new(),
+        }
+    }
+
+    /// Parses `text` and the files it includes
+    pub fn load(&mut self, name: Option<String>, text: String) -> Result<Spanned<Node>, Error> {
+        let file = self.sources.len();
+        self.sources.push(Source {
+            name: name.map(Arc::from),
+            text,
+        });
+
+        let source = &self.sources[file];
+        let mut node = parse_node(&source.text, self.options)
+            .map_err(|e| e.in_file(file, source.name.as_ref()))?;
+        set_file(&mut node, file);
+
+        self.stack.push(file);
+        self.expand(&mut node, file)?;
+        self.stack.pop();
+        Ok(node)
+    }
+
+    /// Loads the file included as `path` from the file `from`.
+    /// `span` is the place of the `#include`
+    pub fn include(
+        &mut self,
+        path: &str,
+        span: Option<Span>,
+        from: Option<usize>,
+    ) -> Result<Spanned<Node>, Error> {
+        let location = span.map(|span| self.location(span));
+        let resolver = match self.resolver {
+            Some(resolver) => resolver,
+            None => {
+                return Err(Error::Include {
+                    location,
+                    kind: IncludeErrorKind::NoResolver,
+                })
+            }
+        };
+
+        let from = from.and_then(|from| self.sources[from].name.as_deref().map(String::as_str));
+        let name = resolver.resolve(path, from);
+
+        let included = |i: &usize| self.sources[*i].name.as_deref() == Some(&name);
+        if let Some(start) = self.stack.iter().position(included) {
+            let cycle = self.stack[start..]
+                .iter()
+                .map(|i| {
+                    self.sources[*i]
+                        .name
+                        .as_deref()
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .chain([name])
+                .collect();
+
+            return Err(Error::Include {
+                location,
+                kind: IncludeErrorKind::Cycle(cycle),
+            });
+        }
+
+        if let Some(node) = self.included.get(&name) {
+            return Ok(node.clone());
+        }
+
+        match resolver.load(&name) {
+            Ok(text) => {
+                let node = self.load(Some(name.clone()), text)?;
+                self.included.insert(name, node.clone());
+                Ok(node)
+            }
+            Err(e) => Err(Error::Include {
+                location,
+                kind: IncludeErrorKind::Load {
+                    name,
+                    message: e.to_string(),
+                },
+            }),
+        }
+    }
+
+    fn expand(&mut self, node: &mut Spanned<Node>, file: usize) -> Result<(), Error> {
+        match &mut node.node {
+            Node::Object(pairs) => {
+                for (_, value) in pairs {
+                    self.expand(value, file)?;
+                }
+            }
+            Node::Array(arr) => {
+                for e in arr {
+                    self.expand(e, file)?;
+                }
+            }
+            Node::TemplateString(parts) => {
+                for part in parts {
+                    if let TemplateNode::Interpolation(value) = &mut part.node {
+                        self.expand(value, file)?;
+                    }
+                }
+            }
+            Node::ReferenceDeclaration { value, .. } => self.expand(value, file)?,
+            Node::Expression(expression) => {
+                for operand in expression.operands_mut() {
+                    self.expand(operand, file)?;
+                }
+            }
+            Node::Include(path) => {
+                let path = path.clone();
+                *node = self.include(&path, node.span, Some(file))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn location(&self, span: Span) -> Box<Location> {
+        let source = &self.sources[span.file];
+        Box::new(Location {
+            span,
+            file: source.name.clone(),
+            snippet: source.text[span.start..span.end].to_string(),
+        })
+    }
+}
+
+/// Sets the file of every span in the tree
+fn set_file(node: &mut Spanned<Node>, file: usize) {
+    if let Some(span) = &mut node.span {
+        span.file = file;
+    }
+
+    match &mut node.node {
+        Node::Object(pairs) => {
+            for (key, value) in pairs {
+                if let Some(span) = &mut key.span {
+                    span.file = file;
+                }
+                set_file(value, file);
+            }
+        }
+        Node::Array(arr) => {
+            for e in arr {
+                set_file(e, file);
+            }
+        }
+        Node::TemplateString(parts) => {
+            for part in parts {
+                if let Some(span) = &mut part.span {
+                    span.file = file;
+                }
+                if let TemplateNode::Interpolation(value) = &mut part.node {
+                    set_file(value, file);
+                }
+            }
+        }
+        Node::ReferenceDeclaration { id, value } => {
+            if let Some(span) = &mut id.span {
+                span.file = file;
+            }
+            set_file(value, file);
+        }
+        Node::Expression(expression) => {
+            for operand in expression.operands_mut() {
+                set_file(operand, file);
+            }
+        }
+        _ => {}
+    }
+}
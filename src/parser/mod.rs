@@ -1,6 +1,210 @@
-use crate::Value;
+use crate::ast::{Node, Spanned};
+use crate::cst::CstNode;
+use crate::resolver::Resolver;
+use crate::{Error, Value};
+use include::Loader;
+pub(crate) use include::Source;
 use pest::Parser;
+use std::fmt;
+mod cst;
+mod dialect;
+mod functions;
+mod include;
 mod parse;
+mod provider;
+
+pub use functions::FunctionRegistry;
+pub use include::{FileResolver, MapResolver, SourceResolver};
+pub use provider::{EnvProvider, MapProvider, Providers, ValueProvider};
+
+/// Language accepted by the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// Plain JSON, RFC 8259
+    Json,
+    /// JSON5: comments, unquoted keys, single quotes, trailing commas, `NaN`, hexadecimal numbers, ...
+    Json5,
+    /// JSON5 with references, template strings, includes and expressions
+    Fson,
+}
+
+/// Part of the syntax that can be disallowed with [`ParseOptions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Comments,
+    UnquotedKeys,
+    SingleQuotes,
+    TrailingCommas,
+    /// `NaN`, `Infinity` and `-Infinity`
+    NonFiniteNumbers,
+    /// Hexadecimal numbers, leading `+`, `.5` and `5.`
+    NumberExtensions,
+    /// Escapes that JSON does not have (`\x41`, `\v`, `\0`, `\'`, ...), line continuations
+    /// and unescaped control characters other than line breaks
+    StringExtensions,
+    /// Unescaped line breaks in strings
+    MultilineStrings,
+    /// Whitespace other than spaces, tabs and line breaks
+    WhitespaceExtensions,
+    /// References and reference declarations
+    References,
+    TemplateStrings,
+    /// `#include "file"`
+    Includes,
+    /// `$(#port + 1)`
+    Expressions,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Feature::Comments => "comments",
+            Feature::UnquotedKeys => "unquoted keys",
+            Feature::SingleQuotes => "single-quoted strings",
+            Feature::TrailingCommas => "trailing commas",
+            Feature::NonFiniteNumbers => "`NaN` and `Infinity`",
+            Feature::NumberExtensions => "hexadecimal numbers, `+` signs and bare decimal points",
+            Feature::StringExtensions => {
+                "JSON5 escapes, line continuations and unescaped control characters"
+            }
+            Feature::MultilineStrings => "line breaks in strings",
+            Feature::WhitespaceExtensions => "whitespace other than spaces, tabs and line breaks",
+            Feature::References => "references",
+            Feature::TemplateStrings => "template strings",
+            Feature::Includes => "includes",
+            Feature::Expressions => "expressions",
+        })
+    }
+}
+
+/// What to do when an object key or a reference id is used twice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Fail with [`SemanticErrorKind::DuplicateKey`](crate::SemanticErrorKind::DuplicateKey)
+    /// or [`SemanticErrorKind::DuplicateId`](crate::SemanticErrorKind::DuplicateId)
+    Error,
+    /// Keep the last value, in the place of the first one
+    LastWins,
+    /// Keep the first value
+    FirstWins,
+}
+
+/// Options of [`parse_with`]. The default ones accept everything FSON has,
+/// [`ParseOptions::dialect`] restricts the syntax to JSON or JSON5
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Resolve references to missing ids and paths to `null`
+    /// instead of failing with [`ReferenceErrorKind::Unresolved`](crate::ReferenceErrorKind::Unresolved)
+    pub unresolved_references_as_null: bool,
+    /// Keep integers that do not fit in `i128` as [`Value::BigInteger`] instead of
+    /// failing with [`SemanticErrorKind::InvalidNumber`](crate::SemanticErrorKind::InvalidNumber)
+    pub big_integers: bool,
+    /// Keys used twice in one object, `{ a: 1, a: 2 }` is `{ a: 2 }` by default
+    pub duplicate_keys: DuplicatePolicy,
+    /// Reference ids declared twice, an error by default
+    pub duplicate_ids: DuplicatePolicy,
+    /// Providers of `${env:NAME}` in template strings, none by default.
+    /// [`EnvProvider`] gives access to environment variables
+    pub providers: Providers,
+    /// Functions that expressions can call, the built-in ones by default
+    pub functions: FunctionRegistry,
+
+    /// Each disallowed [`Feature`] fails with
+    /// [`SemanticErrorKind::NotAllowed`](crate::SemanticErrorKind::NotAllowed)
+    pub allow_comments: bool,
+    pub allow_unquoted_keys: bool,
+    pub allow_single_quotes: bool,
+    pub allow_trailing_commas: bool,
+    pub allow_non_finite_numbers: bool,
+    pub allow_number_extensions: bool,
+    pub allow_string_extensions: bool,
+    pub allow_multiline_strings: bool,
+    pub allow_whitespace_extensions: bool,
+    pub allow_references: bool,
+    pub allow_templates: bool,
+    pub allow_includes: bool,
+    pub allow_expressions: bool,
+}
+
+impl ParseOptions {
+    /// Options that accept only the syntax of `dialect`
+    /// ## Example:
+    /// ```
+    /// let options = ParseOptions {
+    ///     allow_comments: true,
+    ///     ..ParseOptions::dialect(Dialect::Json)
+    /// };
+    /// parse_with(String::from("// JSON with comments\n{}"), &options)
+    /// ```
+    pub fn dialect(dialect: Dialect) -> ParseOptions {
+        let json5 = dialect != Dialect::Json;
+        let fson = dialect == Dialect::Fson;
+
+        ParseOptions {
+            unresolved_references_as_null: false,
+            big_integers: false,
+            duplicate_keys: DuplicatePolicy::LastWins,
+            duplicate_ids: DuplicatePolicy::Error,
+            providers: Providers::default(),
+            functions: FunctionRegistry::default(),
+            allow_comments: json5,
+            allow_unquoted_keys: json5,
+            allow_single_quotes: json5,
+            allow_trailing_commas: json5,
+            allow_non_finite_numbers: json5,
+            allow_number_extensions: json5,
+            allow_string_extensions: json5,
+            allow_multiline_strings: fson,
+            allow_whitespace_extensions: json5,
+            allow_references: fson,
+            allow_templates: fson,
+            allow_includes: fson,
+            allow_expressions: fson,
+        }
+    }
+
+    /// Whether the feature is allowed
+    pub fn allows(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Comments => self.allow_comments,
+            Feature::UnquotedKeys => self.allow_unquoted_keys,
+            Feature::SingleQuotes => self.allow_single_quotes,
+            Feature::TrailingCommas => self.allow_trailing_commas,
+            Feature::NonFiniteNumbers => self.allow_non_finite_numbers,
+            Feature::NumberExtensions => self.allow_number_extensions,
+            Feature::StringExtensions => self.allow_string_extensions,
+            Feature::MultilineStrings => self.allow_multiline_strings,
+            Feature::WhitespaceExtensions => self.allow_whitespace_extensions,
+            Feature::References => self.allow_references,
+            Feature::TemplateStrings => self.allow_templates,
+            Feature::Includes => self.allow_includes,
+            Feature::Expressions => self.allow_expressions,
+        }
+    }
+
+    /// Whether any feature is disallowed
+    fn restricts(&self) -> bool {
+        !(self.allow_comments
+            && self.allow_unquoted_keys
+            && self.allow_single_quotes
+            && self.allow_trailing_commas
+            && self.allow_non_finite_numbers
+            && self.allow_number_extensions
+            && self.allow_string_extensions
+            && self.allow_multiline_strings
+            && self.allow_whitespace_extensions
+            && self.allow_references
+            && self.allow_templates
+            && self.allow_includes
+            && self.allow_expressions)
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions::dialect(Dialect::Fson)
+    }
+}
 
 #[derive(Parser)]
 #[grammar = "parser/grammar/json.pest"]
@@ -16,12 +220,102 @@ struct JsonParser;
 /// }
 /// "#)
 /// ```
-pub fn parse(json: String) -> Result<Value, String> {
+pub fn parse(json: String) -> Result<Value, Error> {
+    parse_with(json, &ParseOptions::default())
+}
+
+/// Parses string with options
+/// ## Example:
+/// ```
+/// parse_with(String::from("#missing"), &ParseOptions {
+///     unresolved_references_as_null: true,
+///     ..Default::default()
+/// })
+/// ```
+pub fn parse_with(json: String, options: &ParseOptions) -> Result<Value, Error> {
+    let mut loader = Loader::new(None, options);
+    let node = loader.load(None, json)?;
+    Resolver::new(&loader.sources, options).resolve(&node)
+}
+
+/// Parses file loaded with `resolver`, together with the files it includes
+/// with `#include "file"`. Each `#include` is replaced with the value of the file,
+/// and reference ids declared in any of the files can be used in all of them
+/// ## Example:
+/// ```
+/// let files = MapResolver::from_iter([
+///     ("main.fson", "{ db: #include 'db.fson', url: `${#db_host}:5432` }"),
+///     ("db.fson", "{ host: #{ #id: 'db_host'; #value: 'localhost'; } }"),
+/// ]);
+/// parse_file("main.fson", &files, &ParseOptions::default())
+/// ```
+pub fn parse_file(
+    name: &str,
+    resolver: &dyn SourceResolver,
+    options: &ParseOptions,
+) -> Result<Value, Error> {
+    let mut loader = Loader::new(Some(resolver), options);
+    let node = loader.include(name, None, None)?;
+    Resolver::new(&loader.sources, options).resolve(&node)
+}
+
+/// Parses `json` into a node tree, with the syntax allowed by `options`
+fn parse_node(json: &str, options: &ParseOptions) -> Result<Spanned<Node>, Error> {
+    match JsonParser::parse(Rule::document, json) {
+        Ok(pairs) => {
+            if options.restricts() {
+                let cst = cst::to_cst(pairs.clone(), json);
+                dialect::check(&cst, json, options)?;
+            }
+            parse::to_node(pairs, options)
+        }
+        Err(error) => Err(Error::from_pest(error, json)),
+    }
+}
+
+/// Parses string without evaluating it: references, template strings and
+/// reference declarations are kept, so the document can be edited and
+/// generated back with [`generator::from`](crate::generator::from)
+/// ## Example:
+/// ```
+/// parse_raw(String::from("{ x: #{ #id: 'x'; #value: 1; }, y: `x is ${#x}` }"))
+/// ```
+pub fn parse_raw(json: String) -> Result<Value, Error> {
+    match JsonParser::parse(Rule::document, json.as_str()) {
+        Ok(pairs) => Ok(parse::to_node(pairs, &ParseOptions::default())?.into_value()),
+        Err(error) => Err(Error::from_pest(error, json.as_str())),
+    }
+}
+
+/// Parses string into a tree where every node keeps its place in the source.
+/// Like [`parse_raw`], references, template strings and reference declarations
+/// are not evaluated
+/// ## Example:
+/// ```
+/// let node = parse_spanned(String::from("{ port: 80 }")).unwrap();
+/// if let Node::Object(pairs) = node.node {
+///     let (key, value) = &pairs[0];
+///     let span = value.span.unwrap();
+///     println!("{} at {}:{}", key.node, span.line, span.column); // port at 1:9
+/// }
+/// ```
+pub fn parse_spanned(json: String) -> Result<Spanned<Node>, Error> {
+    match JsonParser::parse(Rule::document, json.as_str()) {
+        Ok(pairs) => parse::to_node(pairs, &ParseOptions::default()),
+        Err(error) => Err(Error::from_pest(error, json.as_str())),
+    }
+}
+
+/// Parses string into a lossless syntax tree that keeps comments, whitespace
+/// and the exact spelling of every literal
+/// ## Example:
+/// ```
+/// let cst = parse_cst(String::from("[0x1ABC, 'x', ] // comment")).unwrap();
+/// assert_eq!(cst.to_string(), "[0x1ABC, 'x', ] // comment");
+/// ```
+pub fn parse_cst(json: String) -> Result<CstNode, Error> {
     match JsonParser::parse(Rule::document, json.as_str()) {
-        Ok(pairs) => match parse::to_hashmap(pairs) {
-            Ok(result) => Ok(result),
-            Err(_) => Err(String::new()),
-        },
-        Err(e) => Err(e.to_string()),
+        Ok(pairs) => Ok(cst::to_cst(pairs, json.as_str())),
+        Err(error) => Err(Error::from_pest(error, json.as_str())),
     }
 }
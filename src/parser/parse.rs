@@ -1,291 +1,454 @@
+use crate::ast::{ExpressionNode, Node, Spanned, TemplateNode};
+use crate::error::{Error, Location, SemanticErrorKind};
+use crate::expression::{BinaryOperator, UnaryOperator};
+use crate::parser::{DuplicatePolicy, ParseOptions};
 use crate::types::*;
-use crate::utils::{sanitize_string, stringify_value};
-
-fn get_all_ref_paths(
-    pair: Pair<'_, Rule>,
-    mut path: String,
-    refs_manager: ReferencesManager,
-) -> HashMap<String, Pair<'_, Rule>> {
-    let mut paths: HashMap<String, Pair<'_, Rule>> = HashMap::new();
-    let inner = pair.clone().into_inner();
-    let rule = pair.as_rule();
-
-    if rule == Rule::object_pair {
-        let mut cloned_inner = inner.clone();
-        let index = parse_pair(cloned_inner.next().unwrap(), refs_manager.clone());
-        let value = cloned_inner.next().unwrap();
-
-        let mut index_value: String = String::new();
-        match index {
-            Value::Identifier(str) => index_value = str,
-            Value::String(str) => index_value = str,
-            _ => {}
-        }
-
-        paths.insert(path.clone() + &index_value, value.clone());
+use crate::utils::unescape;
 
-        if value.as_rule() == Rule::object {
-            path += format!("{}/", index_value).as_str();
-        }
-    }
+pub fn to_node(mut pairs: Pairs<'_, Rule>, options: &ParseOptions) -> Result<Spanned<Node>, Error> {
+    Builder { options }.parse_pair(pairs.next().unwrap())
+}
 
-    inner.for_each(|e| {
-        paths.extend(get_all_ref_paths(e, path.clone(), refs_manager.clone()));
-    });
-    paths
+/// Builds nodes from pest pairs
+struct Builder<'a> {
+    options: &'a ParseOptions,
 }
 
-fn check_pair_for_ref(pair: Pair<'_, Rule>) -> HashMap<String, Pair<'_, Rule>> {
-    let mut refs: HashMap<String, Pair<'_, Rule>> = HashMap::new();
-    let mut inner = pair.clone().into_inner();
-    let rule = pair.as_rule();
-
-    if rule == Rule::ref_value {
-        let id = sanitize_string(
-            inner
-                .next()
-                .unwrap()
-                .into_inner()
-                .next()
-                .unwrap()
-                .as_span()
-                .as_str()
-                .to_string(),
-        );
-
-        let val = inner.next().unwrap().into_inner().next().unwrap();
-        refs.insert(id.to_string(), val);
-    } else {
-        inner.for_each(|e| {
-            refs.extend(check_pair_for_ref(e));
-        });
+impl Builder<'_> {
+    fn parse_pair(&self, pair: Pair<'_, Rule>) -> Result<Spanned<Node>, Error> {
+        let node = match pair.as_rule() {
+            Rule::expression
+            | Rule::logical_or
+            | Rule::logical_and
+            | Rule::equality
+            | Rule::comparison
+            | Rule::sum
+            | Rule::product
+            | Rule::unary => return self.parse_expression(pair),
+            Rule::expression_value => return self.parse_pair(pair.into_inner().next().unwrap()),
+            Rule::call => {
+                let mut inner = pair.clone().into_inner();
+                let function = inner.next().unwrap().as_str().to_string();
+                let arguments = inner
+                    .map(|argument| self.parse_pair(argument))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Node::Expression(Box::new(ExpressionNode::Call {
+                    function,
+                    arguments,
+                }))
+            }
+            Rule::variable => Node::Expression(Box::new(ExpressionNode::Variable(
+                self.parse_variable(pair.clone())?,
+            ))),
+            Rule::number => self.parse_number(pair.clone())?,
+            Rule::string => self.parse_string(pair.clone())?,
+            Rule::not_a_number => Node::NaN,
+            Rule::null => Node::Null,
+            Rule::reference => self.parse_reference(pair.clone())?,
+            Rule::object => self.parse_object(pair.clone())?,
+            Rule::boolean => Node::Boolean(pair.as_span().as_str() == "true"),
+            Rule::ref_value => self.parse_ref_value(pair.clone())?,
+            Rule::include => {
+                let name = pair.clone().into_inner().next().unwrap();
+                Node::Include(unescape_span(name.as_span())?)
+            }
+            Rule::array => self.parse_array(pair.clone())?,
+            _ => Node::Null,
+        };
+        Ok(spanned(node, &pair))
     }
-    refs
-}
 
-pub fn to_hashmap(mut pairs: Pairs<'_, Rule>) -> Result<Value, ()> {
-    let first_pair = pairs.next().unwrap();
-
-    let empty_refs_manager = ReferencesManager {
-        ref_paths: HashMap::new(),
-        refs: HashMap::new(),
-    };
-
-    let ref_paths = get_all_ref_paths(
-        first_pair.clone(),
-        "/".to_string(),
-        empty_refs_manager.clone(),
-    );
-    let refs = check_pair_for_ref(first_pair.clone());
-
-    let mut value_ref_paths: HashMap<String, Value> = HashMap::new();
-    let mut value_refs: HashMap<String, Value> = HashMap::new();
-
-    refs.keys().for_each(|key| {
-        let value = &refs[key];
-        value_refs.insert(
-            key.clone(),
-            parse_pair(
-                value.clone(),
-                ReferencesManager {
-                    ref_paths: value_ref_paths.clone(),
-                    refs: value_refs.clone(),
-                },
-            ),
-        );
-    });
-
-    ref_paths.keys().for_each(|key| {
-        let value = &ref_paths[key];
-        value_ref_paths.insert(
-            key.clone(),
-            parse_pair(
-                value.clone(),
-                ReferencesManager {
-                    ref_paths: value_ref_paths.clone(),
-                    refs: value_refs.clone(),
-                },
-            ),
-        );
-    });
-    Ok(parse_pair(
-        first_pair,
-        ReferencesManager {
-            ref_paths: value_ref_paths,
-            refs: value_refs,
-        },
-    ))
-}
+    fn parse_ref_value(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let mut id: Option<Spanned<String>> = None;
+        let mut value: Option<Spanned<Node>> = None;
 
-fn parse_pair(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> Value {
-    match pair.as_rule() {
-        Rule::number => parse_number(pair),
-        Rule::string => parse_string(pair, refs_manager),
-        Rule::not_a_number => Value::NaN,
-        Rule::null => Value::Null,
-        Rule::identifier => Value::Identifier(pair.as_span().as_str().to_string()),
-        Rule::reference => parse_reference(pair, refs_manager),
-        Rule::object => parse_object(pair, refs_manager),
-        Rule::boolean => Value::Boolean(pair.as_span().as_str() == "true"),
-        Rule::ref_value => parse_ref_value(pair, refs_manager),
-        Rule::array => parse_array(pair, refs_manager),
-        _ => Value::Null,
+        for e in pair.into_inner() {
+            let inner = e.clone().into_inner().next().unwrap();
+            match e.as_rule() {
+                Rule::ref_value_id => id = Some(spanned(unescape_span(inner.as_span())?, &e)),
+                _ => value = Some(self.parse_pair(inner)?),
+            }
+        }
+
+        Ok(Node::ReferenceDeclaration {
+            id: id.unwrap(),
+            value: Box::new(value.unwrap()),
+        })
     }
-}
 
-fn parse_ref_value(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> Value {
-    let mut inner = pair.into_inner();
-    inner.next();
-    parse_pair(
-        inner.next().unwrap().into_inner().next().unwrap(),
-        refs_manager,
-    )
-}
+    fn parse_array(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let mut arr: Vec<Spanned<Node>> = vec![];
+        let inner = pair.into_inner();
+
+        for e in inner {
+            arr.push(self.parse_pair(e)?);
+        }
 
-fn parse_array(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> Value {
-    let mut arr: Vec<Value> = vec![];
-    let inner = pair.into_inner();
+        Ok(Node::Array(arr))
+    }
 
-    inner.for_each(|e| {
-        arr.push(parse_pair(e, refs_manager.clone()));
-    });
+    fn parse_number(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let span = pair.as_span();
+        let str = span.as_str();
 
-    Value::Array(arr)
-}
+        let (negative, unsigned) = match str.as_bytes()[0] {
+            b'-' => (true, &str[1..]),
+            b'+' => (false, &str[1..]),
+            _ => (false, str),
+        };
+        let sign = if negative { "-" } else { "" };
 
-fn parse_number(pair: Pair<'_, Rule>) -> Value {
-    let span = pair.as_span();
-    let str = span.as_str();
-
-    match str {
-        "Infinity" => Value::Infinity,
-        "-Infinity" => Value::NegativeInfinity,
-        _ => {
-            if let Ok(float) = str.parse::<f64>() {
-                Value::Number(float)
-            } else if let Ok(hexadecimal) = usize::from_str_radix(str.trim_start_matches("0x"), 16)
-            {
-                Value::Number(hexadecimal as f64)
+        if unsigned == "Infinity" {
+            return Ok(if negative {
+                Node::NegativeInfinity
             } else {
-                Value::Number(0.0)
-            }
+                Node::Infinity
+            });
+        }
+
+        if let Some(hexadecimal) = unsigned
+            .strip_prefix("0x")
+            .or_else(|| unsigned.strip_prefix("0X"))
+        {
+            let integer = u128::from_str_radix(hexadecimal, 16)
+                .ok()
+                .and_then(|integer| signed(negative, integer));
+            return match integer {
+                // Integers have no negative zero, `-0x0` keeps its sign as a float
+                Some(0) if negative => Ok(Node::Number(-0.0)),
+                Some(integer) => Ok(Node::Integer(integer)),
+                None if self.options.big_integers => Ok(Node::BigInteger(format!(
+                    "{}{}",
+                    sign,
+                    hex_to_decimal(hexadecimal)
+                ))),
+                None => Err(Error::Semantic {
+                    location: Some(Box::new(Location::from_pest(span))),
+                    kind: SemanticErrorKind::InvalidNumber(str.to_string()),
+                }),
+            };
+        }
+
+        if unsigned.bytes().all(|b| b.is_ascii_digit()) {
+            let integer = unsigned
+                .parse::<u128>()
+                .ok()
+                .and_then(|integer| signed(negative, integer));
+            return match integer {
+                Some(0) if negative => Ok(Node::Number(-0.0)),
+                Some(integer) => Ok(Node::Integer(integer)),
+                None if self.options.big_integers => {
+                    Ok(Node::BigInteger(format!("{}{}", sign, unsigned)))
+                }
+                None => Err(Error::Semantic {
+                    location: Some(Box::new(Location::from_pest(span))),
+                    kind: SemanticErrorKind::InvalidNumber(str.to_string()),
+                }),
+            };
+        }
+
+        match str.parse::<f64>() {
+            Ok(float) => Ok(Node::Number(float)),
+            Err(_) => Err(Error::Semantic {
+                location: Some(Box::new(Location::from_pest(span))),
+                kind: SemanticErrorKind::InvalidNumber(str.to_string()),
+            }),
         }
     }
-}
 
-fn parse_template_string(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> String {
-    let mut result_string = String::new();
-    let mut inner = pair.into_inner();
-    let template_string = inner.next().unwrap();
-    let template_string_inner = template_string.into_inner();
-
-    template_string_inner.for_each(|template_pair| {
-        let pair_rule = template_pair.as_rule();
-        if pair_rule == Rule::template_char {
-            result_string.push_str(template_pair.as_span().as_str());
-        } else if pair_rule == Rule::interpolation_template {
-            let mut pair_inner = template_pair.into_inner();
-            let interpolation_first = pair_inner.next().unwrap();
-            let value = parse_pair(interpolation_first.clone(), refs_manager.clone());
-
-            match value {
-                Value::String(str) => result_string.push_str(str.as_str()),
-                _ => {
-                    result_string.push_str(stringify_value(value, 0, false).as_str());
+    fn parse_template_string(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let mut parts: Vec<Spanned<TemplateNode>> = vec![];
+        let mut chars: Option<pest::Span<'_>> = None;
+
+        for template_pair in pair.into_inner() {
+            let pair_rule = template_pair.as_rule();
+            if pair_rule == Rule::template_char {
+                let span = template_pair.as_span();
+                chars = Some(match chars {
+                    Some(chars) => chars.start_pos().span(&span.end_pos()),
+                    None => span,
+                });
+            } else if pair_rule == Rule::interpolation_template {
+                if let Some(chars) = chars.take() {
+                    parts.push(template_chars(chars)?);
                 }
+
+                let inner = template_pair.clone().into_inner().next().unwrap();
+                let part = match inner.as_rule() {
+                    Rule::variable => TemplateNode::Variable(self.parse_variable(inner)?),
+                    _ => TemplateNode::Interpolation(self.parse_pair(inner)?),
+                };
+                parts.push(spanned(part, &template_pair));
             }
         }
-    });
 
-    result_string
-}
+        if let Some(chars) = chars {
+            parts.push(template_chars(chars)?);
+        }
 
-fn parse_string(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> Value {
-    let mut inner = pair.clone().into_inner();
-    let inner_first = inner.next();
+        Ok(Node::TemplateString(parts))
+    }
 
-    if let Some(first) = inner_first {
-        let first_span = first.as_span();
-        let mut str: String = String::new();
+    fn parse_string(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let mut inner = pair.into_inner();
+        let inner_first = inner.next();
 
-        match first.as_rule() {
-            Rule::single_quotes_string => {
-                str = first_span.as_str().to_string();
-            }
-            Rule::double_quotes_string => {
-                str = first_span.as_str().to_string();
+        if let Some(first) = inner_first {
+            match first.as_rule() {
+                Rule::template_string => self.parse_template_string(first),
+                _ => Ok(Node::String(unescape_span(first.as_span())?)),
             }
-            Rule::template_string => {
-                str = parse_template_string(pair, refs_manager);
+        } else {
+            Ok(Node::String(String::new()))
+        }
+    }
+
+    /// Object key or reference: identifier or string without interpolations
+    fn parse_key(&self, pair: Pair<'_, Rule>) -> Result<Spanned<String>, Error> {
+        if pair.as_rule() == Rule::identifier {
+            return Ok(spanned(unescape_span(pair.as_span())?, &pair));
+        }
+
+        let mut key = String::new();
+        match self.parse_string(pair.clone())? {
+            Node::String(str) => key = str,
+            Node::TemplateString(parts) => {
+                for part in parts {
+                    match part.node {
+                        TemplateNode::String(str) => key += &str,
+                        TemplateNode::Interpolation(_) | TemplateNode::Variable(_) => {
+                            return Err(Error::Semantic {
+                                location: Some(Box::new(Location::from_pest(pair.as_span()))),
+                                kind: SemanticErrorKind::InterpolatedKey,
+                            })
+                        }
+                    }
+                }
             }
             _ => {}
         }
-        Value::String(sanitize_string(str.to_string()).to_string())
-    } else {
-        Value::String(String::new())
+        Ok(spanned(key, &pair))
     }
-}
 
-fn parse_object(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> Value {
-    let mut hashmap: HashMap<String, Value> = HashMap::new();
-    let inner = pair.into_inner();
+    fn parse_object(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let mut pairs: Vec<(Spanned<String>, Spanned<Node>)> = vec![];
+        // Index and key span of every key in `pairs`
+        let mut keys: HashMap<String, (usize, pest::Span<'_>)> = HashMap::new();
+        let inner = pair.into_inner();
+
+        for object_pair in inner {
+            let mut pair_inner = object_pair.into_inner();
+            let key_pair = pair_inner.next().unwrap();
+            let index = self.parse_key(key_pair.clone())?;
+            let value = self.parse_pair(pair_inner.next().unwrap())?;
+
+            match keys.get(&index.node) {
+                None => {
+                    keys.insert(index.node.clone(), (pairs.len(), key_pair.as_span()));
+                    pairs.push((index, value));
+                }
+                Some((i, first)) => match self.options.duplicate_keys {
+                    DuplicatePolicy::Error => {
+                        return Err(Error::Semantic {
+                            location: Some(Box::new(Location::from_pest(key_pair.as_span()))),
+                            kind: SemanticErrorKind::DuplicateKey {
+                                key: index.node,
+                                first: Some(Box::new(Location::from_pest(*first))),
+                            },
+                        })
+                    }
+                    DuplicatePolicy::LastWins => pairs[*i].1 = value,
+                    DuplicatePolicy::FirstWins => {}
+                },
+            }
+        }
 
-    inner.for_each(|object_pair| {
-        let mut pair_inner = object_pair.into_inner();
-        let index = parse_pair(pair_inner.next().unwrap(), refs_manager.clone());
-        let value = parse_pair(pair_inner.next().unwrap(), refs_manager.clone());
+        Ok(Node::Object(pairs))
+    }
 
-        let mut index_value = String::new();
+    fn parse_reference(&self, pair: Pair<'_, Rule>) -> Result<Node, Error> {
+        let mut inner = pair.into_inner();
+        let inner_first = inner.next().unwrap();
+
+        match inner_first.as_rule() {
+            Rule::ref_path => Ok(Node::Reference(ReferenceAsValue::Path(
+                self.parse_path(inner_first)?,
+            ))),
+            Rule::ref_relative => {
+                let mut relative = inner_first.into_inner();
+                let parents = relative.next().unwrap().as_str().matches("..").count();
+                Ok(Node::Reference(ReferenceAsValue::Relative {
+                    parents,
+                    path: self.parse_path(relative.next().unwrap())?,
+                }))
+            }
+            _ => {
+                let id = self.parse_key(inner_first)?.node;
+                Ok(Node::Reference(ReferenceAsValue::Id(id)))
+            }
+        }
+    }
 
-        match index {
-            Value::Identifier(identifier) => index_value = identifier,
-            Value::String(str) => index_value = str,
-            _ => {}
+    /// Expression of `${...}`. Precedence levels with a single operand are left out,
+    /// so `${#x}` is the reference itself
+    fn parse_expression(&self, pair: Pair<'_, Rule>) -> Result<Spanned<Node>, Error> {
+        let span = pair.as_span();
+        let rule = pair.as_rule();
+        let mut inner = pair.into_inner();
+        let first = inner.next().unwrap();
+
+        match rule {
+            Rule::expression => {
+                let condition = self.parse_pair(first)?;
+                match (inner.next(), inner.next()) {
+                    (Some(then), Some(otherwise)) => Ok(expression(
+                        ExpressionNode::Conditional {
+                            condition,
+                            then: self.parse_pair(then)?,
+                            otherwise: self.parse_pair(otherwise)?,
+                        },
+                        span,
+                    )),
+                    _ => Ok(condition),
+                }
+            }
+            Rule::unary if first.as_rule() == Rule::unary_operator => {
+                let operator = match first.as_str() {
+                    "-" => UnaryOperator::Negate,
+                    _ => UnaryOperator::Not,
+                };
+                let operand = self.parse_pair(inner.next().unwrap())?;
+                Ok(expression(
+                    ExpressionNode::Unary { operator, operand },
+                    span,
+                ))
+            }
+            Rule::unary => self.parse_pair(first),
+            _ => {
+                // Operators of one level are applied from left to right
+                let mut left = self.parse_pair(first)?;
+                while let Some(operator) = inner.next() {
+                    let right = inner.next().unwrap();
+                    let right_span = right.as_span();
+                    left = expression(
+                        ExpressionNode::Binary {
+                            operator: binary_operator(operator.as_str()),
+                            left,
+                            right: self.parse_pair(right)?,
+                        },
+                        span.start_pos().span(&right_span.end_pos()),
+                    );
+                }
+                Ok(left)
+            }
         }
+    }
 
-        hashmap.insert(index_value, value);
-    });
+    /// `provider:name` or `provider?:name`
+    fn parse_variable(&self, pair: Pair<'_, Rule>) -> Result<Variable, Error> {
+        let mut variable = Variable {
+            provider: String::new(),
+            name: String::new(),
+            optional: false,
+        };
+        for variable_pair in pair.into_inner() {
+            match variable_pair.as_rule() {
+                Rule::identifier => variable.provider = self.parse_key(variable_pair)?.node,
+                Rule::variable_optional => variable.optional = true,
+                _ => variable.name = variable_pair.as_str().to_string(),
+            }
+        }
+        Ok(variable)
+    }
 
-    Value::Object(hashmap)
+    /// Segments of `ref_path`
+    fn parse_path(&self, pair: Pair<'_, Rule>) -> Result<Path, Error> {
+        let mut segments: Vec<String> = vec![];
+        for path_pair in pair.into_inner() {
+            match path_pair.as_rule() {
+                Rule::ref_path_index => segments.push(path_pair.as_str().to_string()),
+                _ => segments.push(self.parse_key(path_pair)?.node),
+            }
+        }
+        Ok(Path::from(segments))
+    }
 }
 
-fn parse_reference(pair: Pair<'_, Rule>, refs_manager: ReferencesManager) -> Value {
-    let mut inner = pair.clone().into_inner();
-    let inner_first = inner.next().unwrap();
-    let first_rule = inner_first.as_rule();
+fn spanned<T>(node: T, pair: &Pair<'_, Rule>) -> Spanned<T> {
+    Spanned::new(node, Some(Span::from_pest(pair.as_span())))
+}
 
-    if first_rule == Rule::identifier {
-        let id = inner_first.as_span().as_str();
+fn expression(expression: ExpressionNode, span: pest::Span<'_>) -> Spanned<Node> {
+    Spanned::new(
+        Node::Expression(Box::new(expression)),
+        Some(Span::from_pest(span)),
+    )
+}
 
-        let value = refs_manager.get_by_id(id.to_string());
-        value.unwrap_or(Value::Null)
-    } else if first_rule == Rule::string {
-        let id = parse_pair(inner_first, refs_manager.clone());
-        let mut id_str = String::new();
+fn binary_operator(symbol: &str) -> BinaryOperator {
+    match symbol {
+        "||" => BinaryOperator::Or,
+        "&&" => BinaryOperator::And,
+        "==" => BinaryOperator::Equal,
+        "!=" => BinaryOperator::NotEqual,
+        "<" => BinaryOperator::Less,
+        "<=" => BinaryOperator::LessOrEqual,
+        ">" => BinaryOperator::Greater,
+        ">=" => BinaryOperator::GreaterOrEqual,
+        "+" => BinaryOperator::Add,
+        "-" => BinaryOperator::Subtract,
+        "*" => BinaryOperator::Multiply,
+        "/" => BinaryOperator::Divide,
+        _ => BinaryOperator::Remainder,
+    }
+}
 
-        if let Value::String(i) = id {
-            id_str = i
-        }
+fn template_chars(span: pest::Span<'_>) -> Result<Spanned<TemplateNode>, Error> {
+    Ok(Spanned::new(
+        TemplateNode::String(unescape_span(span)?),
+        Some(Span::from_pest(span)),
+    ))
+}
 
-        let value = refs_manager.get_by_id(id_str);
-        value.unwrap_or(Value::Null)
-    } else {
-        let path_inner = inner_first.into_inner();
-        let mut path_str = String::new();
+/// Decodes escape sequences of the string characters in `span`
+fn unescape_span(span: pest::Span<'_>) -> Result<String, Error> {
+    unescape(span.as_str()).map_err(|e| match e {
+        Error::Semantic { kind, .. } => Error::Semantic {
+            location: Some(Box::new(Location::from_pest(span))),
+            kind,
+        },
+        e => e,
+    })
+}
 
-        path_inner.for_each(
-            |path_pair| match parse_pair(path_pair, refs_manager.clone()) {
-                Value::Identifier(identifier) => {
-                    path_str += format!("/{}", identifier).as_str();
-                }
-                Value::String(str) => {
-                    path_str += format!("/{}", str).as_str();
-                }
-                _ => {}
-            },
-        );
+/// Integer with the sign, `None` if it does not fit in `i128`
+fn signed(negative: bool, integer: u128) -> Option<i128> {
+    if negative {
+        0i128.checked_sub_unsigned(integer)
+    } else {
+        i128::try_from(integer).ok()
+    }
+}
 
-        let value = refs_manager.get_by_path(path_str);
-        value.unwrap_or(Value::Null)
+/// Decimal digits of a hexadecimal number of any size
+fn hex_to_decimal(hexadecimal: &str) -> String {
+    // Decimal digits from the lowest one
+    let mut digits: Vec<u32> = vec![0];
+    for c in hexadecimal.chars() {
+        let mut carry = c.to_digit(16).unwrap();
+        for digit in digits.iter_mut() {
+            let value = *digit * 16 + carry;
+            *digit = value % 10;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
     }
+    digits
+        .iter()
+        .rev()
+        .map(|digit| char::from_digit(*digit, 10).unwrap())
+        .collect()
 }
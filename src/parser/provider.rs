@@ -0,0 +1,96 @@
+use crate::types::{HashMap, Value};
+use std::fmt;
+use std::sync::Arc;
+
+/// Source of the values of `${provider:NAME}` in template strings
+pub trait ValueProvider: Send + Sync {
+    /// Value of the variable `name`, `None` if it is not set
+    fn get(&self, name: &str) -> Option<Value>;
+}
+
+/// Environment variables of the process, as strings.
+/// It is not registered by default, so documents can not read them unless allowed
+/// ## Example:
+/// ```
+/// let mut options = ParseOptions::default();
+/// options.providers.insert("env", EnvProvider);
+/// parse_with(String::from("{ url: `${env:DATABASE_URL}`, port: `${env?:PORT}` }"), &options)
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvProvider;
+
+impl ValueProvider for EnvProvider {
+    fn get(&self, name: &str) -> Option<Value> {
+        std::env::var(name).ok().map(Value::String)
+    }
+}
+
+/// Values kept in memory by name
+/// ## Example:
+/// ```
+/// let mut options = ParseOptions::default();
+/// options.providers.insert("env", MapProvider::from_iter([
+///     ("DATABASE_URL", Value::String(String::from("postgres://localhost"))),
+/// ]));
+/// parse_with(String::from("`${env:DATABASE_URL}`"), &options)
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MapProvider {
+    values: HashMap<String, Value>,
+}
+
+impl MapProvider {
+    pub fn new() -> MapProvider {
+        MapProvider::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: Value) {
+        self.values.insert(name.into(), value);
+    }
+}
+
+impl<K: Into<String>> FromIterator<(K, Value)> for MapProvider {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> MapProvider {
+        MapProvider {
+            values: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect(),
+        }
+    }
+}
+
+impl ValueProvider for MapProvider {
+    fn get(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+}
+
+/// Value providers by the name used in template strings, there are none by default
+#[derive(Clone, Default)]
+pub struct Providers {
+    providers: HashMap<String, Arc<dyn ValueProvider>>,
+}
+
+impl Providers {
+    /// Registers `provider` as `name`, replacing the one that had the name
+    pub fn insert(&mut self, name: impl Into<String>, provider: impl ValueProvider + 'static) {
+        self.providers.insert(name.into(), Arc::new(provider));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.providers.remove(name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn ValueProvider> {
+        self.providers.get(name).map(|provider| provider.as_ref())
+    }
+}
+
+impl fmt::Debug for Providers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.providers.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
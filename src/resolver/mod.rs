@@ -0,0 +1,546 @@
+use crate::ast::{ExpressionNode, Node, Spanned, TemplateNode};
+use crate::error::{
+    Error, ExpressionErrorKind, IncludeErrorKind, Location, ReferenceErrorKind, SemanticErrorKind,
+    VariableErrorKind,
+};
+use crate::expression::{self, BinaryOperator, ValueType};
+use crate::parser::{DuplicatePolicy, FunctionRegistry, ParseOptions, Providers, Source};
+use crate::stringify_json::{stringify_with, FormatOptions, KeyQuotes};
+use crate::types::*;
+use crate::utils::{escape, is_identifier, is_index, text};
+use std::fmt;
+
+impl Value {
+    /// Evaluates references, template strings and reference declarations,
+    /// with the same id and path rules as [`parser::parse`](crate::parser::parse)
+    /// ## Example:
+    /// ```
+    /// Value::Array(vec![
+    ///     Value::ReferenceDeclaration { id: "x".to_string(), value: Box::new(Value::Number(1.0)) },
+    ///     Value::Reference(ReferenceAsValue::Id("x".to_string())),
+    /// ])
+    /// .resolve() // Ok([1, 1])
+    /// ```
+    pub fn resolve(&self) -> Result<Value, Error> {
+        let node = Spanned::new(Node::from_value(self), None);
+        Resolver::new(&[], &ParseOptions::default()).resolve(&node)
+    }
+}
+
+/// Value that a reference can point to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Target {
+    Id(String),
+    Path(Path),
+}
+
+impl Target {
+    /// Target of a reference written at `location`,
+    /// `None` if a relative reference goes above the root
+    fn from_reference(reference: &ReferenceAsValue, location: &Path) -> Option<Target> {
+        match reference {
+            ReferenceAsValue::Id(id) => Some(Target::Id(id.clone())),
+            ReferenceAsValue::Path(path) => Some(Target::Path(path.clone())),
+            ReferenceAsValue::Relative { parents, path } => {
+                let segments = location.segments();
+                let len = segments.len().checked_sub(parents + 1)?;
+                Some(Target::Path(
+                    segments[..len].iter().chain(path.segments()).collect(),
+                ))
+            }
+        }
+    }
+}
+
+/// Written as a reference: `#id`, `#/servers/0/"a/b"`
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = |key: &str| {
+            if is_identifier(key) {
+                key.to_string()
+            } else {
+                format!("\"{}\"", escape(key, '"'))
+            }
+        };
+
+        match self {
+            Target::Id(id) => write!(f, "#{}", key(id)),
+            Target::Path(path) if path.is_root() => write!(f, "#/"),
+            Target::Path(path) => {
+                write!(f, "#")?;
+                for segment in path.segments() {
+                    if is_index(segment) {
+                        write!(f, "/{}", segment)?;
+                    } else {
+                        write!(f, "/{}", key(segment))?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Evaluates references, template strings and reference declarations of a node tree.
+///
+/// Referenced values are evaluated in dependency order, so a reference
+/// may point to a value that uses other references, wherever it is declared
+pub(crate) struct Resolver<'a> {
+    /// Files the nodes were parsed from, used for error snippets
+    sources: &'a [Source],
+    /// Resolve missing references to `null` instead of failing
+    unresolved_as_null: bool,
+    duplicate_ids: DuplicatePolicy,
+    providers: Providers,
+    functions: FunctionRegistry,
+    /// Referenced nodes with their paths
+    targets: HashMap<Target, (&'a Spanned<Node>, Path)>,
+    /// Declared ids, for duplicate errors
+    ids: HashMap<String, &'a Spanned<String>>,
+    manager: ReferencesManager,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(sources: &'a [Source], options: &ParseOptions) -> Resolver<'a> {
+        Resolver {
+            sources,
+            unresolved_as_null: options.unresolved_references_as_null,
+            duplicate_ids: options.duplicate_ids,
+            providers: options.providers.clone(),
+            functions: options.functions.clone(),
+            targets: HashMap::new(),
+            ids: HashMap::new(),
+            manager: ReferencesManager {
+                refs: HashMap::new(),
+                ref_paths: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn resolve(mut self, root: &'a Spanned<Node>) -> Result<Value, Error> {
+        self.targets
+            .insert(Target::Path(Path::root()), (root, Path::root()));
+        self.collect_targets(root, Path::root(), true)?;
+
+        for target in self.order(root)? {
+            let (node, path) = &self.targets[&target];
+            let value = self.evaluate(node, path)?;
+            match target {
+                Target::Id(id) => self.manager.refs.insert(id, value),
+                Target::Path(path) => self.manager.ref_paths.insert(path, value),
+            };
+        }
+
+        self.evaluate(root, &Path::root())
+    }
+
+    /// Registers reference declarations by id, and object values and array items by path.
+    /// Declarations do not add a path segment: `{ a: #{ #id: 'x'; #value: { b: 1 }; } }`
+    /// has `#/a/b`. `addressable` is `false` inside values that can not be referenced by path
+    fn collect_targets(
+        &mut self,
+        node: &'a Spanned<Node>,
+        path: Path,
+        addressable: bool,
+    ) -> Result<(), Error> {
+        match &node.node {
+            Node::Object(pairs) => {
+                for (key, value) in pairs {
+                    let value_path = path.join(key.node.as_str());
+                    if addressable {
+                        self.targets.insert(
+                            Target::Path(value_path.clone()),
+                            (value, value_path.clone()),
+                        );
+                    }
+                    self.collect_targets(value, value_path, addressable)?;
+                }
+            }
+            Node::Array(arr) => {
+                for (i, e) in arr.iter().enumerate() {
+                    let item_path = path.join(i.to_string());
+                    if addressable {
+                        self.targets
+                            .insert(Target::Path(item_path.clone()), (e, item_path.clone()));
+                    }
+                    self.collect_targets(e, item_path, addressable)?;
+                }
+            }
+            Node::TemplateString(parts) => {
+                for part in parts {
+                    if let TemplateNode::Interpolation(value) = &part.node {
+                        self.collect_targets(value, path.clone(), false)?;
+                    }
+                }
+            }
+            Node::Expression(expression) => {
+                for operand in expression.operands() {
+                    self.collect_targets(operand, path.clone(), false)?;
+                }
+            }
+            Node::ReferenceDeclaration { id, value } => {
+                match self.ids.get(&id.node) {
+                    None => {
+                        self.ids.insert(id.node.clone(), id);
+                        self.targets
+                            .insert(Target::Id(id.node.clone()), (value, path.clone()));
+                    }
+                    // The same declaration, from a file included more than once
+                    Some(first) if first.span.is_some() && first.span == id.span => {}
+                    Some(first) => match self.duplicate_ids {
+                        DuplicatePolicy::Error => {
+                            return Err(Error::Semantic {
+                                location: self.location(id.span),
+                                kind: SemanticErrorKind::DuplicateId {
+                                    id: id.node.clone(),
+                                    first: self.location(first.span),
+                                },
+                            })
+                        }
+                        DuplicatePolicy::LastWins => {
+                            self.targets
+                                .insert(Target::Id(id.node.clone()), (value, path.clone()));
+                        }
+                        DuplicatePolicy::FirstWins => {}
+                    },
+                }
+                self.collect_targets(value, path, addressable)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Referenced targets ordered so that every target comes after the targets it uses
+    fn order(&self, root: &'a Spanned<Node>) -> Result<Vec<Target>, Error> {
+        let mut visited: HashMap<Target, bool> = HashMap::new();
+        let mut stack: Vec<Target> = vec![];
+        let mut order: Vec<Target> = vec![];
+
+        for dependency in dependencies(root, &Path::root(), false) {
+            self.visit(dependency, &mut visited, &mut stack, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// Depth-first walk over the dependency graph.
+    /// `visited` holds `false` while the dependencies of a target are being visited
+    fn visit(
+        &self,
+        dependency: Dependency<'a>,
+        visited: &mut HashMap<Target, bool>,
+        stack: &mut Vec<Target>,
+        order: &mut Vec<Target>,
+    ) -> Result<(), Error> {
+        let Dependency {
+            reference,
+            span,
+            path,
+            skippable,
+        } = dependency;
+        let target = Target::from_reference(reference, &path);
+        let node = target.as_ref().and_then(|target| self.targets.get(target));
+        let (target, (node, path)) = match (target, node) {
+            (Some(target), Some(node)) => (target, node),
+            // Fails when it is evaluated
+            _ if self.unresolved_as_null || skippable => return Ok(()),
+            _ => return Err(self.unresolved(reference, span)),
+        };
+
+        match visited.get(&target) {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                let start = stack.iter().position(|e| *e == target).unwrap();
+                let cycle = stack[start..]
+                    .iter()
+                    .chain([&target])
+                    .map(|e| e.to_string())
+                    .collect();
+
+                return Err(Error::Reference {
+                    location: self.location(span),
+                    kind: ReferenceErrorKind::Cycle(cycle),
+                });
+            }
+            None => {}
+        }
+
+        visited.insert(target.clone(), false);
+        stack.push(target.clone());
+
+        for dependency in dependencies(node, path, false) {
+            self.visit(dependency, visited, stack, order)?;
+        }
+
+        stack.pop();
+        visited.insert(target.clone(), true);
+        order.push(target);
+        Ok(())
+    }
+
+    fn unresolved(&self, reference: &ReferenceAsValue, span: Option<Span>) -> Error {
+        let location = self.location(span);
+        let reference = match &location {
+            Some(location) if !location.snippet.is_empty() => location.snippet.clone(),
+            _ => stringify_with(
+                Value::Reference(reference.clone()),
+                &FormatOptions {
+                    key_quotes: KeyQuotes::WhenNeeded,
+                    ..Default::default()
+                },
+            ),
+        };
+
+        Error::Reference {
+            location,
+            kind: ReferenceErrorKind::Unresolved(reference),
+        }
+    }
+
+    fn location(&self, span: Option<Span>) -> Option<Box<Location>> {
+        span.map(|span| match self.sources.get(span.file) {
+            Some(source) => Box::new(Location {
+                span,
+                file: source.name.clone(),
+                snippet: source.text[span.start..span.end].to_string(),
+            }),
+            None => Box::new(Location {
+                span,
+                file: None,
+                snippet: String::new(),
+            }),
+        })
+    }
+
+    /// Value of `variable` from its provider, `None` if it is optional and not set
+    fn variable(&self, variable: &Variable, span: Option<Span>) -> Result<Option<Value>, Error> {
+        let kind = match self.providers.get(&variable.provider) {
+            Some(provider) => match provider.get(&variable.name) {
+                Some(value) => return Ok(Some(value)),
+                None if variable.optional => return Ok(None),
+                None => VariableErrorKind::Unset {
+                    provider: variable.provider.clone(),
+                    name: variable.name.clone(),
+                },
+            },
+            None => VariableErrorKind::UnknownProvider(variable.provider.clone()),
+        };
+
+        Err(Error::Variable {
+            location: self.location(span),
+            kind,
+        })
+    }
+
+    /// `path` is where the node is, for relative references
+    fn evaluate(&self, node: &Spanned<Node>, path: &Path) -> Result<Value, Error> {
+        Ok(match &node.node {
+            Node::Infinity => Value::Infinity,
+            Node::NegativeInfinity => Value::NegativeInfinity,
+            Node::Null => Value::Null,
+            Node::NaN => Value::NaN,
+            Node::String(str) => Value::String(str.clone()),
+            Node::Number(num) => Value::Number(*num),
+            Node::Integer(integer) => Value::Integer(*integer),
+            Node::BigInteger(integer) => Value::BigInteger(integer.clone()),
+            Node::Boolean(bool) => Value::Boolean(*bool),
+            Node::Object(pairs) => {
+                let mut object: IndexMap<String, Value> = IndexMap::new();
+                for (key, value) in pairs {
+                    let value = self.evaluate(value, &path.join(key.node.as_str()))?;
+                    object.insert(key.node.clone(), value);
+                }
+                Value::Object(object)
+            }
+            Node::Array(arr) => {
+                let mut values: Vec<Value> = vec![];
+                for (i, e) in arr.iter().enumerate() {
+                    values.push(self.evaluate(e, &path.join(i.to_string()))?);
+                }
+                Value::Array(values)
+            }
+            Node::TemplateString(parts) => {
+                let mut result_string = String::new();
+                for part in parts {
+                    let value = match &part.node {
+                        TemplateNode::String(str) => {
+                            result_string.push_str(str);
+                            continue;
+                        }
+                        TemplateNode::Interpolation(value) => self.evaluate(value, path)?,
+                        TemplateNode::Variable(variable) => {
+                            match self.variable(variable, part.span)? {
+                                Some(value) => value,
+                                None => continue,
+                            }
+                        }
+                    };
+                    result_string.push_str(&text(value, false));
+                }
+                Value::String(result_string)
+            }
+            Node::Reference(reference) => {
+                let value = match Target::from_reference(reference, path) {
+                    Some(Target::Id(id)) => self.manager.get_by_id(id),
+                    Some(Target::Path(path)) => self.manager.get_by_path(&path),
+                    None => None,
+                };
+                match value {
+                    Some(value) => value,
+                    None if self.unresolved_as_null => Value::Null,
+                    // Only references in skippable operands are left unresolved by `order`
+                    None => return Err(self.unresolved(reference, node.span)),
+                }
+            }
+            Node::ReferenceDeclaration { value, .. } => self.evaluate(value, path)?,
+            Node::Include(_) => {
+                return Err(Error::Include {
+                    location: self.location(node.span),
+                    kind: IncludeErrorKind::NoResolver,
+                })
+            }
+            Node::Expression(expression) => {
+                self.evaluate_expression(expression, node.span, path)?
+            }
+        })
+    }
+
+    /// `span` is the place of the whole expression, errors of operators point at it
+    fn evaluate_expression(
+        &self,
+        expression: &ExpressionNode,
+        span: Option<Span>,
+        path: &Path,
+    ) -> Result<Value, Error> {
+        let result = match expression {
+            ExpressionNode::Variable(variable) => {
+                return Ok(self.variable(variable, span)?.unwrap_or(Value::Null))
+            }
+            ExpressionNode::Unary { operator, operand } => {
+                expression::unary(*operator, self.evaluate(operand, path)?)
+            }
+            ExpressionNode::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.evaluate(left, path)?;
+                // `false && x` and `true || x` do not evaluate `x`
+                match (operator, &left) {
+                    (BinaryOperator::And, Value::Boolean(false))
+                    | (BinaryOperator::Or, Value::Boolean(true)) => return Ok(left),
+                    _ => expression::binary(*operator, left, self.evaluate(right, path)?),
+                }
+            }
+            ExpressionNode::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => match self.evaluate(condition, path)? {
+                Value::Boolean(true) => return self.evaluate(then, path),
+                Value::Boolean(false) => return self.evaluate(otherwise, path),
+                value => {
+                    return Err(Error::Expression {
+                        location: self.location(condition.span),
+                        kind: ExpressionErrorKind::Condition(ValueType::of(&value)),
+                    })
+                }
+            },
+            ExpressionNode::Call {
+                function,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate(argument, path))
+                    .collect::<Result<Vec<Value>, Error>>()?;
+                // Errors of the function point at the call
+                return self
+                    .functions
+                    .call(function, &arguments)
+                    .map_err(|error| error.or_at(self.location(span)));
+            }
+        };
+
+        result.map_err(|kind| Error::Expression {
+            location: self.location(span),
+            kind,
+        })
+    }
+}
+
+/// Reference used inside of a node
+struct Dependency<'a> {
+    reference: &'a ReferenceAsValue,
+    span: Option<Span>,
+    /// Path of the value that has the reference.
+    /// Values inside interpolations and expressions are at the path of the template string or expression
+    path: Path,
+    /// Operand that `&&`, `||` or `?:` may not evaluate,
+    /// it can reference nothing unless it is evaluated
+    skippable: bool,
+}
+
+/// References used inside of a node at `path`, in document order
+fn dependencies<'a>(node: &'a Spanned<Node>, path: &Path, skippable: bool) -> Vec<Dependency<'a>> {
+    let mut references: Vec<Dependency> = vec![];
+
+    match &node.node {
+        Node::Reference(reference) => references.push(Dependency {
+            reference,
+            span: node.span,
+            path: path.clone(),
+            skippable,
+        }),
+        Node::Object(pairs) => {
+            for (key, value) in pairs {
+                references.extend(dependencies(
+                    value,
+                    &path.join(key.node.as_str()),
+                    skippable,
+                ));
+            }
+        }
+        Node::Array(arr) => {
+            for (i, e) in arr.iter().enumerate() {
+                references.extend(dependencies(e, &path.join(i.to_string()), skippable));
+            }
+        }
+        Node::TemplateString(parts) => {
+            for part in parts {
+                if let TemplateNode::Interpolation(value) = &part.node {
+                    references.extend(dependencies(value, path, skippable));
+                }
+            }
+        }
+        Node::ReferenceDeclaration { value, .. } => {
+            references.extend(dependencies(value, path, skippable))
+        }
+        Node::Expression(expression) => match expression.as_ref() {
+            ExpressionNode::Binary {
+                operator: BinaryOperator::And | BinaryOperator::Or,
+                left,
+                right,
+            } => {
+                references.extend(dependencies(left, path, skippable));
+                references.extend(dependencies(right, path, true));
+            }
+            ExpressionNode::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                references.extend(dependencies(condition, path, skippable));
+                references.extend(dependencies(then, path, true));
+                references.extend(dependencies(otherwise, path, true));
+            }
+            expression => {
+                for operand in expression.operands() {
+                    references.extend(dependencies(operand, path, skippable));
+                }
+            }
+        },
+        _ => {}
+    }
+    references
+}
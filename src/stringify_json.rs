@@ -1,53 +1,430 @@
+use super::expression::Expression;
 use super::types::*;
 use super::utils::*;
 
-const ADD_TO_IDENT: usize = 2;
-const NEWLINE: &str = "\n";
+pub(crate) const NEWLINE: &str = "\n";
+
+/// Indentation of one nesting level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// Number of spaces
+    Spaces(usize),
+    /// One tab
+    Tabs,
+}
+
+/// When object keys and references are quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyQuotes {
+    /// `{ "key": 1 }`, `#"id"`
+    Always,
+    /// Only keys that are not identifiers: `{ key: 1, "two words": 2 }`, `#id`
+    WhenNeeded,
+}
+
+/// Quotes of strings and quoted keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quote {
+    /// `"string"`
+    Double,
+    /// `'string'`
+    Single,
+}
+
+/// Options of [`stringify_with`], the default ones print the same as [`stringify`]
+/// ## Example:
+/// ```
+/// let options = FormatOptions {
+///     indent: Indent::Tabs,
+///     key_quotes: KeyQuotes::WhenNeeded,
+///     max_width: Some(80),
+///     ..Default::default()
+/// };
+/// stringify_with(value, &options)
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub indent: Indent,
+    pub key_quotes: KeyQuotes,
+    pub quote: Quote,
+    /// Comma after the last item of objects and arrays that span multiple lines
+    pub trailing_commas: bool,
+    /// Objects and arrays that fit in the width are printed on one line,
+    /// others get an item per line. Tabs count as one character.
+    /// With `None` objects always span multiple lines and arrays never do
+    pub max_width: Option<usize>,
+    /// Newline at the end of the output
+    pub final_newline: bool,
+    /// Everything on one line without spaces
+    pub minify: bool,
+    /// Sort keys of objects, see [`Value::sort_keys`]
+    pub sort_keys: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            indent: Indent::Spaces(2),
+            key_quotes: KeyQuotes::Always,
+            quote: Quote::Double,
+            trailing_commas: true,
+            max_width: None,
+            final_newline: false,
+            minify: false,
+            sort_keys: false,
+        }
+    }
+}
 
 /// Stringify value
 /// ## Example:
 /// ```
 /// stringify(Value::Number(1.0), 0, false)
 /// ```
-pub fn stringify(val: Value, mut ident: usize, minify: bool) -> String {
-    match val {
-        Value::Object(object) => {
-            let mut str = String::new();
-            str += "{".to_string().as_str();
-            let mut add_to_ident = ADD_TO_IDENT;
-            let mut newline = NEWLINE;
-            if minify {
-                ident = 0;
-                add_to_ident = 0;
-                newline = "";
-            };
-            let newline_with_ident = String::from(newline) + &" ".repeat(ident);
-            for pair in object.clone() {
-                let (k, v) = pair;
-                str += format!(
-                    "{}{}\"{}\":{}{},",
-                    if object.is_empty() { "" } else { newline },
-                    " ".repeat(ident + add_to_ident),
-                    sanitize_string(k),
-                    if minify { "" } else { " " },
-                    stringify(v, ident + add_to_ident, minify)
-                )
-                .as_str();
+pub fn stringify(val: Value, ident: usize, minify: bool) -> String {
+    let options = FormatOptions {
+        minify,
+        ..Default::default()
+    };
+    stringify_indented(val, &" ".repeat(ident), NEWLINE, &options)
+}
+
+/// Stringify value with options
+/// ## Example:
+/// ```
+/// stringify_with(Value::Number(1.0), &FormatOptions { final_newline: true, ..Default::default() })
+/// ```
+pub fn stringify_with(val: Value, options: &FormatOptions) -> String {
+    stringify_indented(val, "", NEWLINE, options)
+}
+
+/// Text of an expression without `${}`: `#port + 1`
+pub(crate) fn stringify_expression(expression: &Expression) -> String {
+    let options = FormatOptions {
+        key_quotes: KeyQuotes::WhenNeeded,
+        ..Default::default()
+    };
+    Printer {
+        options: &options,
+        newline: NEWLINE,
+    }
+    .expression(expression)
+}
+
+/// Stringify value that starts on a line indented by `indent`,
+/// lines are separated by `newline`
+pub(crate) fn stringify_indented(
+    mut val: Value,
+    indent: &str,
+    newline: &str,
+    options: &FormatOptions,
+) -> String {
+    if options.sort_keys {
+        val.sort_keys();
+    }
+
+    let printer = Printer { options, newline };
+    let mut str = printer.print(&val, indent, indent.chars().count());
+    if options.final_newline {
+        str += newline;
+    }
+    str
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    newline: &'a str,
+}
+
+impl Printer<'_> {
+    /// `indent` is the indentation of the line the value is on,
+    /// `column` is the width of the text before the value on that line
+    fn print(&self, value: &Value, indent: &str, column: usize) -> String {
+        if self.options.minify {
+            return self.inline(value);
+        }
+
+        match value {
+            Value::Object(object) if !object.is_empty() => {
+                if self.options.max_width.is_some() && self.fits(value, column) {
+                    return self.inline(value);
+                }
+
+                let inner = self.nested(indent);
+                let items = object
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = format!("{}: ", self.key(k));
+                        let column = inner.chars().count() + key.chars().count();
+                        key + &self.print(v, &inner, column)
+                    })
+                    .collect();
+                self.block("{", items, "}", indent)
+            }
+
+            Value::Array(arr) if !arr.is_empty() => match self.options.max_width {
+                None => {
+                    let items: Vec<String> =
+                        arr.iter().map(|e| self.print(e, indent, column)).collect();
+                    format!("[{}]", items.join(", "))
+                }
+                Some(_) if self.fits(value, column) => self.inline(value),
+                Some(_) => {
+                    let inner = self.nested(indent);
+                    let column = inner.chars().count();
+                    let items = arr.iter().map(|e| self.print(e, &inner, column)).collect();
+                    self.block("[", items, "]", indent)
+                }
+            },
+
+            Value::TemplateString(parts) => {
+                let mut str = String::from("`");
+                for part in parts {
+                    match part {
+                        TemplateValue::String(s) => str += &escape(s, '`'),
+                        TemplateValue::Interpolation(Value::Expression(expression)) => {
+                            str += &format!("${{{}}}", self.expression(expression));
+                        }
+                        TemplateValue::Interpolation(interpolation) => {
+                            let column = column + str.chars().count() + 2;
+                            str += &format!("${{{}}}", self.print(interpolation, indent, column));
+                        }
+                        TemplateValue::Variable(variable) => str += &format!("${{{}}}", variable),
+                    }
+                }
+                str + "`"
+            }
+
+            Value::ReferenceDeclaration { id, value } => {
+                let prefix = format!("#{{ #id: {}; #value: ", self.string(id));
+                let column = column + prefix.chars().count();
+                format!("{}{}; }}", prefix, self.print(value, indent, column))
             }
-            str += format!(
-                "{}{}",
+
+            _ => self.inline(value),
+        }
+    }
+
+    /// Value on one line
+    fn inline(&self, value: &Value) -> String {
+        let whitespace = if self.options.minify { "" } else { " " };
+
+        match value {
+            Value::Number(num) => float_literal(*num),
+            Value::Integer(integer) => integer.to_string(),
+            Value::BigInteger(integer) => integer.clone(),
+            Value::Boolean(bool) => bool.to_string(),
+            Value::Infinity => String::from("Infinity"),
+            Value::NegativeInfinity => String::from("-Infinity"),
+            Value::Null => String::from("null"),
+            Value::NaN => String::from("NaN"),
+            Value::String(str) => self.string(str),
+            Value::Identifier(identifier) => identifier.clone(),
+
+            Value::Object(object) => {
                 if object.is_empty() {
-                    ""
-                } else {
-                    newline_with_ident.as_str()
-                },
-                "}",
-            )
-            .as_str();
-            str
-        }
-        _ => {
-            stringify_value(val, ident, minify)
+                    return String::from("{}");
+                }
+                let pairs: Vec<String> = object
+                    .iter()
+                    .map(|(k, v)| format!("{}:{}{}", self.key(k), whitespace, self.inline(v)))
+                    .collect();
+                format!(
+                    "{{{}{}{}}}",
+                    whitespace,
+                    pairs.join(&format!(",{}", whitespace)),
+                    whitespace
+                )
+            }
+
+            Value::Array(arr) => {
+                let items: Vec<String> = arr.iter().map(|e| self.inline(e)).collect();
+                format!("[{}]", items.join(&format!(",{}", whitespace)))
+            }
+
+            Value::TemplateString(parts) => {
+                let mut str = String::from("`");
+                for part in parts {
+                    match part {
+                        TemplateValue::String(s) => str += &escape(s, '`'),
+                        TemplateValue::Interpolation(interpolation) => {
+                            str += &format!("${{{}}}", self.interpolation(interpolation));
+                        }
+                        TemplateValue::Variable(variable) => str += &format!("${{{}}}", variable),
+                    }
+                }
+                str + "`"
+            }
+
+            Value::ReferenceDeclaration { id, value } => format!(
+                "#{{{}#id:{}{};{}#value:{}{};{}}}",
+                whitespace,
+                whitespace,
+                self.string(id),
+                whitespace,
+                whitespace,
+                self.inline(value),
+                whitespace,
+            ),
+
+            Value::Include(name) => format!("#include {}", self.string(name)),
+
+            Value::Expression(expression) => format!("$({})", self.expression(expression)),
+
+            Value::Reference(reference_value) => match reference_value {
+                ReferenceAsValue::Id(id) => format!("#{}", self.key(id)),
+                ReferenceAsValue::Path(path) => {
+                    let path: Vec<String> =
+                        path.segments().iter().map(|e| self.segment(e)).collect();
+                    format!("#/{}", path.join("/"))
+                }
+                ReferenceAsValue::Relative { parents, path } => {
+                    let mut str = match parents {
+                        0 => String::from("#./"),
+                        _ => format!("#{}", "../".repeat(*parents)),
+                    };
+                    let path: Vec<String> =
+                        path.segments().iter().map(|e| self.segment(e)).collect();
+                    str += &path.join("/");
+                    str
+                }
+            },
+        }
+    }
+
+    /// Text of `${...}`
+    fn interpolation(&self, value: &Value) -> String {
+        match value {
+            Value::Expression(expression) => self.expression(expression),
+            _ => self.inline(value),
+        }
+    }
+
+    /// Expression without `${}`
+    fn expression(&self, expression: &Expression) -> String {
+        let whitespace = if self.options.minify { "" } else { " " };
+
+        match expression {
+            Expression::Variable(variable) => variable.to_string(),
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| self.interpolation(argument))
+                    .collect();
+                format!(
+                    "{}({})",
+                    function,
+                    arguments.join(&format!(",{}", whitespace))
+                )
+            }
+            Expression::Unary { operator, operand } => {
+                format!("{}{}", operator, self.operand(operand, u8::MAX))
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let precedence = operator.precedence();
+                format!(
+                    "{}{}{}{}{}",
+                    self.operand(left, precedence),
+                    whitespace,
+                    operator,
+                    whitespace,
+                    self.operand(right, precedence + 1)
+                )
+            }
+            Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => format!(
+                "{}{}?{}{}{}:{}{}",
+                self.operand(condition, 1),
+                whitespace,
+                whitespace,
+                self.interpolation(then),
+                whitespace,
+                whitespace,
+                self.interpolation(otherwise)
+            ),
         }
     }
+
+    /// Operand of an operator, in parentheses if it is an expression
+    /// with a precedence lower than `precedence`. `?:` has the lowest one
+    fn operand(&self, value: &Value, precedence: u8) -> String {
+        let operand_precedence = match value {
+            Value::Expression(expression) => match expression.as_ref() {
+                Expression::Binary { operator, .. } => operator.precedence(),
+                Expression::Conditional { .. } => 0,
+                _ => u8::MAX,
+            },
+            _ => u8::MAX,
+        };
+
+        if operand_precedence < precedence {
+            format!("({})", self.interpolation(value))
+        } else {
+            self.interpolation(value)
+        }
+    }
+
+    /// Items on separate lines
+    fn block(&self, open: &str, items: Vec<String>, close: &str, indent: &str) -> String {
+        let inner = self.nested(indent);
+        let mut str = String::from(open);
+        for (i, item) in items.iter().enumerate() {
+            str += self.newline;
+            str += &inner;
+            str += item;
+            if i + 1 < items.len() || self.options.trailing_commas {
+                str += ",";
+            }
+        }
+        str + self.newline + indent + close
+    }
+
+    fn fits(&self, value: &Value, column: usize) -> bool {
+        match self.options.max_width {
+            Some(width) => column + self.inline(value).chars().count() <= width,
+            None => true,
+        }
+    }
+
+    fn nested(&self, indent: &str) -> String {
+        match self.options.indent {
+            Indent::Spaces(width) => format!("{}{}", indent, " ".repeat(width)),
+            Indent::Tabs => format!("{}\t", indent),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        if self.options.key_quotes == KeyQuotes::WhenNeeded && is_identifier(key) {
+            key.to_string()
+        } else {
+            self.string(key)
+        }
+    }
+
+    /// Reference path segment, array indices are written like keys that need no quotes
+    fn segment(&self, segment: &str) -> String {
+        if self.options.key_quotes == KeyQuotes::WhenNeeded && is_index(segment) {
+            segment.to_string()
+        } else {
+            self.key(segment)
+        }
+    }
+
+    fn string(&self, str: &str) -> String {
+        let quote = match self.options.quote {
+            Quote::Double => '"',
+            Quote::Single => '\'',
+        };
+        format!("{}{}{}", quote, escape(str, quote), quote)
+    }
 }
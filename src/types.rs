@@ -1,17 +1,137 @@
+use crate::error::{Error, SemanticErrorKind};
+use crate::expression::Expression;
 pub use crate::parser::Rule;
+pub use indexmap::IndexMap;
 pub use pest::iterators::{Pair, Pairs};
 pub use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TemplateValue {
     String(String),
     Interpolation(Value),
+    Variable(Variable),
+}
+
+/// Variable of a [`ValueProvider`](crate::parser::ValueProvider) in a template string:
+/// `${env:PORT}`, or `${env?:PORT}` that is empty when `PORT` is not set
+/// ## Example:
+/// ```
+/// Variable { provider: String::from("env"), name: String::from("PORT"), optional: true } // env?:PORT
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Variable {
+    pub provider: String,
+    pub name: String,
+    pub optional: bool,
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let optional = if self.optional { "?" } else { "" };
+        write!(f, "{}{}:{}", self.provider, optional, self.name)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ReferenceAsValue {
+    /// `#id`
     Id(String),
-    Path(Vec<String>),
+    /// `#/servers/0/host`, from the root of the document
+    Path(Path),
+    /// `#./host`, `#../db/host`, from the object or array the reference is in.
+    /// `parents` is the number of `../`
+    Relative { parents: usize, path: Path },
+}
+
+/// Keys and array indices that lead to a value from the root of the document.
+/// Written as a JSON Pointer (RFC 6901): `/servers/0/host`, with `~` and `/`
+/// in keys escaped as `~0` and `~1`
+/// ## Example:
+/// ```
+/// let path = Path::from(vec![String::from("a/b"), String::from("c")]);
+/// assert_eq!(path.to_string(), "/a~1b/c");
+/// assert_eq!(Path::parse("/a~1b/c").unwrap(), path);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Path {
+    segments: Vec<String>,
+}
+
+impl Path {
+    /// Path of the root value, written as an empty string
+    pub fn root() -> Path {
+        Path::default()
+    }
+
+    /// Parses a JSON Pointer: an empty string or segments that start with `/`
+    pub fn parse(pointer: &str) -> Result<Path, Error> {
+        if pointer.is_empty() {
+            return Ok(Path::root());
+        }
+
+        let invalid = || Error::Semantic {
+            location: None,
+            kind: SemanticErrorKind::InvalidPath(pointer.to_string()),
+        };
+        let rest = pointer.strip_prefix('/').ok_or_else(invalid)?;
+
+        let mut segments: Vec<String> = vec![];
+        for segment in rest.split('/') {
+            let mut unescaped = String::new();
+            let mut chars = segment.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '~' => match chars.next() {
+                        Some('0') => unescaped.push('~'),
+                        Some('1') => unescaped.push('/'),
+                        _ => return Err(invalid()),
+                    },
+                    c => unescaped.push(c),
+                }
+            }
+            segments.push(unescaped);
+        }
+        Ok(Path { segments })
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Path of a key or an index inside of the value at this path
+    pub fn join(&self, segment: impl Into<String>) -> Path {
+        let mut segments = self.segments.clone();
+        segments.push(segment.into());
+        Path { segments }
+    }
+}
+
+impl From<Vec<String>> for Path {
+    fn from(segments: Vec<String>) -> Path {
+        Path { segments }
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for Path {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Path {
+        Path {
+            segments: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            write!(f, "/{}", segment.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -37,6 +157,22 @@ pub enum Value {
     /// ```
     Number(f64),
 
+    /// Integer that is kept exactly, number literals without
+    /// a fraction or an exponent are parsed to it: `42`, `-0x10`, `+0xFF`
+    /// ## Example:
+    /// ```
+    /// Value::Integer(18446744073709551615)
+    /// ```
+    Integer(i128),
+
+    /// Integer that does not fit in `i128`, as decimal digits with an optional `-`.
+    /// Only parsed with [`ParseOptions::big_integers`](crate::parser::ParseOptions::big_integers)
+    /// ## Example:
+    /// ```
+    /// Value::BigInteger(String::from("-340282366920938463463374607431768211456"))
+    /// ```
+    BigInteger(String),
+
     /// ## Example:
     /// ```
     /// Value::Boolean(true)
@@ -50,7 +186,7 @@ pub enum Value {
     /// ```
     Identifier(String),
 
-    /// Object: `{ k: v, ... }`
+    /// Object: `{ k: v, ... }`, keys are kept in the order they were inserted
     /// ## Example:
     /// ```
     /// Value::Object(object!(
@@ -58,7 +194,7 @@ pub enum Value {
     ///     String::from("two") => Value::Number(2.0))
     /// ))
     /// ```
-    Object(HashMap<String, Value>),
+    Object(IndexMap<String, Value>),
 
     /// Array: `[..., ...]`
     /// ## Example:
@@ -78,7 +214,7 @@ pub enum Value {
     /// ## Example:
     /// ```
     /// Value::Reference(ReferenceAsValue::Id("id".to_string()))
-    /// Value::Reference(ReferenceAsValue::Path(vec![ String::from("path"), String::from("to"), String::from("object") ]))
+    /// Value::Reference(ReferenceAsValue::Path(Path::from(vec![ String::from("path"), String::from("to"), String::from("object") ])))
     /// ```
     Reference(ReferenceAsValue),
 
@@ -94,24 +230,217 @@ pub enum Value {
     /// Value::ReferenceDeclaration { id: "id".to_string(), value: Box::new("any value".to_string()) }
     /// ```
     ReferenceDeclaration { id: String, value: Box<Value> },
+
+    /// Included file: `#include "db.fson"`, replaced with the value of the file when
+    /// it is parsed with [`parser::parse_file`](crate::parser::parse_file)
+    /// ## Example:
+    /// ```
+    /// Value::Include(String::from("db.fson"))
+    /// ```
+    Include(String),
+
+    /// Expression: `$(#port + 1)`, or `${#debug ? "verbose" : "info"}` in a template string.
+    /// `$(...)` evaluates to the value of any type, `${...}` to its text
+    /// ## Example:
+    /// ```
+    /// Value::Expression(Box::new(Expression::Binary {
+    ///     operator: BinaryOperator::Add,
+    ///     left: Value::Reference(ReferenceAsValue::Id(String::from("port"))),
+    ///     right: Value::Integer(1),
+    /// }))
+    /// ```
+    Expression(Box<Expression>),
+}
+
+/// Number converted by [`Value::as_f64`], [`Value::as_i64`] or [`Value::as_u64`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Conversion<T> {
+    /// The number is the same
+    Exact(T),
+    /// The number was rounded or its fraction was dropped
+    Lossy(T),
+}
+
+impl<T> Conversion<T> {
+    /// Converted number, exact or not
+    pub fn value(self) -> T {
+        match self {
+            Conversion::Exact(value) | Conversion::Lossy(value) => value,
+        }
+    }
+
+    /// Converted number, `None` if it is not exact
+    pub fn exact(self) -> Option<T> {
+        match self {
+            Conversion::Exact(value) => Some(value),
+            Conversion::Lossy(_) => None,
+        }
+    }
+
+    pub fn is_lossy(&self) -> bool {
+        matches!(self, Conversion::Lossy(_))
+    }
+}
+
+impl Value {
+    /// Number as `f64`, `None` if the value is not a number
+    /// ## Example:
+    /// ```
+    /// Value::Integer(1).as_f64() // Some(Conversion::Exact(1.0))
+    /// Value::Integer(i64::MAX as i128).as_f64() // Some(Conversion::Lossy(9223372036854775807.0))
+    /// ```
+    pub fn as_f64(&self) -> Option<Conversion<f64>> {
+        Some(match self {
+            Value::Number(num) => Conversion::Exact(*num),
+            Value::NaN => Conversion::Exact(f64::NAN),
+            Value::Infinity => Conversion::Exact(f64::INFINITY),
+            Value::NegativeInfinity => Conversion::Exact(f64::NEG_INFINITY),
+            Value::Integer(integer) => {
+                let float = *integer as f64;
+                // `i128::MAX as f64` is 2^127, which is out of range of `i128`
+                if float as i128 == *integer && float != i128::MAX as f64 {
+                    Conversion::Exact(float)
+                } else {
+                    Conversion::Lossy(float)
+                }
+            }
+            Value::BigInteger(integer) => {
+                let float = integer.parse::<f64>().ok()?;
+                if format!("{:.0}", float) == *integer {
+                    Conversion::Exact(float)
+                } else {
+                    Conversion::Lossy(float)
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Number as `i64`, `None` if the value is not a number or is out of range
+    /// ## Example:
+    /// ```
+    /// Value::Number(1.5).as_i64() // Some(Conversion::Lossy(1))
+    /// ```
+    pub fn as_i64(&self) -> Option<Conversion<i64>> {
+        match self {
+            Value::Integer(integer) => i64::try_from(*integer).ok().map(Conversion::Exact),
+            Value::Number(num) if *num >= i64::MIN as f64 && *num < i64::MAX as f64 => {
+                Some(float_to_integer(*num, *num as i64))
+            }
+            _ => None,
+        }
+    }
+
+    /// Number as `u64`, `None` if the value is not a number or is out of range
+    /// ## Example:
+    /// ```
+    /// Value::Integer(-1).as_u64() // None
+    /// ```
+    pub fn as_u64(&self) -> Option<Conversion<u64>> {
+        match self {
+            Value::Integer(integer) => u64::try_from(*integer).ok().map(Conversion::Exact),
+            Value::Number(num) if *num > -1.0 && *num < u64::MAX as f64 => {
+                Some(float_to_integer(*num, *num as u64))
+            }
+            _ => None,
+        }
+    }
+
+    /// Sorts keys of all objects in the value, including nested ones
+    /// ## Example:
+    /// ```
+    /// let mut value = parse(String::from("{ b: 1, a: { d: 2, c: 3 } }")).unwrap();
+    /// value.sort_keys();
+    /// generator::from(value) // keys: a, c, d, b
+    /// ```
+    pub fn sort_keys(&mut self) {
+        match self {
+            Value::Object(object) => {
+                object.sort_keys();
+                object.values_mut().for_each(Value::sort_keys);
+            }
+            Value::Array(arr) => arr.iter_mut().for_each(Value::sort_keys),
+            Value::TemplateString(parts) => {
+                for part in parts {
+                    if let TemplateValue::Interpolation(value) = part {
+                        value.sort_keys();
+                    }
+                }
+            }
+            Value::ReferenceDeclaration { value, .. } => value.sort_keys(),
+            Value::Expression(expression) => match expression.as_mut() {
+                Expression::Unary { operand, .. } => operand.sort_keys(),
+                Expression::Binary { left, right, .. } => {
+                    left.sort_keys();
+                    right.sort_keys();
+                }
+                Expression::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    condition.sort_keys();
+                    then.sort_keys();
+                    otherwise.sort_keys();
+                }
+                Expression::Variable(_) => {}
+                Expression::Call { arguments, .. } => {
+                    arguments.iter_mut().for_each(Value::sort_keys)
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// `integer` converted from `float`, lossy if the fraction was dropped
+fn float_to_integer<T>(float: f64, integer: T) -> Conversion<T> {
+    if float.fract() == 0.0 {
+        Conversion::Exact(integer)
+    } else {
+        Conversion::Lossy(integer)
+    }
+}
+
+/// Byte range in the source together with the line and column of its start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset after the last character
+    pub end: usize,
+    /// Line of `start`, starting from 1
+    pub line: usize,
+    /// Column of `start`, starting from 1
+    pub column: usize,
+    /// File of the span: 0 is the parsed input, included files follow in the order they were loaded
+    pub file: usize,
+}
+
+impl Span {
+    pub(crate) fn from_pest(span: pest::Span<'_>) -> Span {
+        let (line, column) = span.start_pos().line_col();
+        Span {
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+            file: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ReferencesManager {
     pub refs: HashMap<String, Value>,
-    pub ref_paths: HashMap<String, Value>,
+    pub ref_paths: HashMap<Path, Value>,
 }
 
 impl ReferencesManager {
-    pub fn get_by_path(self, path: String) -> Option<Value> {
-        if self.ref_paths.contains_key(&path.clone()) {
-            let value = &self.ref_paths[&path];
-            Some(value.clone())
-        } else {
-            None
-        }
+    pub fn get_by_path(&self, path: &Path) -> Option<Value> {
+        self.ref_paths.get(path).cloned()
     }
-    pub fn get_by_id(self, id: String) -> Option<Value> {
+    pub fn get_by_id(&self, id: String) -> Option<Value> {
         if self.refs.contains_key(&id.clone()) {
             let value = &self.refs[&id];
             Some(value.clone())
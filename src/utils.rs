@@ -1,91 +1,163 @@
 use super::{
+    error::{Error, SemanticErrorKind},
     stringify_json,
-    types::{ReferenceAsValue, Value},
-    TemplateValue,
+    types::Value,
 };
 
-pub fn sanitize_string(str: String) -> String {
-    str.replace('\"', "\\\"")
-        .replace("\\'", "'")
-        .replace("\\`", "`")
-        .replace("\\n", "\n")
-        .replace("\\t", "\t")
-        .replace("\\r", "\n")
-}
+/// Decodes escape sequences of a string literal written without quotes:
+/// `\"`, `\'`, `` \` ``, `\\`, `\/`, `\b`, `\f`, `\n`, `\r`, `\t`, `\v`, `\0`, `\xXX`
+/// and `\uXXXX`, where UTF-16 surrogate pairs are joined into one character.
+/// A backslash before a line break removes both, any other escaped character is kept as is.
+/// Fails on a surrogate without its pair
+/// ## Example:
+/// ```
+/// unescape("\\u0041\\ud83d\\ude00\\n") // Ok("A😀\n")
+/// ```
+pub fn unescape(str: &str) -> Result<String, Error> {
+    let mut unescaped = String::with_capacity(str.len());
+    let mut chars = str.char_indices();
 
-pub fn stringify_value(value: Value, ident: usize, minify: bool) -> String {
-    match value {
-        Value::Number(num) => num.to_string(),
-        Value::Boolean(bool) => bool.to_string(),
-        Value::Infinity => String::from("Infinity"),
-        Value::NegativeInfinity => String::from("-Infinity"),
-        Value::Null => String::from("null"),
-        Value::NaN => String::from("NaN"),
-        Value::String(str) => format!("\"{}\"", sanitize_string(str)),
-
-        Value::Array(arr) => {
-            let mut str = String::from("[");
-            for (i, e) in arr.iter().enumerate() {
-                str.push_str(
-                    format!(
-                        "{}{}",
-                        stringify_value(e.clone(), ident, minify),
-                        if i == arr.len() - 1 {
-                            ""
-                        } else if minify {
-                            ","
-                        } else {
-                            ", "
-                        }
-                    )
-                    .as_str(),
-                );
-            }
-            str + "]"
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
         }
 
-        Value::Object(_) => stringify_json::stringify(value, ident, minify),
+        // Escape sequence of `len` characters
+        let invalid = |len: usize| {
+            let end = str[start..]
+                .char_indices()
+                .nth(len)
+                .map_or(str.len(), |(i, _)| start + i);
+            Error::Semantic {
+                location: None,
+                kind: SemanticErrorKind::InvalidEscape(str[start..end].to_string()),
+            }
+        };
+        match chars.next().map(|(_, c)| c) {
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('v') => unescaped.push('\u{b}'),
+            Some('0') => unescaped.push('\0'),
+            Some('x') => {
+                let code = str
+                    .get(start + 2..start + 4)
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .ok_or_else(|| invalid(4))?;
+                unescaped.push(char::from_u32(code).unwrap());
+                chars.next();
+                chars.next();
+            }
+            // Line continuation
+            Some('\r') => {
+                if str[start + 2..].starts_with('\n') {
+                    chars.next();
+                }
+            }
+            Some('\n' | '\u{2028}' | '\u{2029}') => {}
+            Some('u') => {
+                let high = hex_escape(&str[start..]).ok_or_else(|| invalid(6))?;
+                for _ in 0..4 {
+                    chars.next();
+                }
 
-        Value::TemplateString(val) => {
-            let mut str = String::from("`");
-            for e in val {
-                match e {
-                    TemplateValue::String(s) => str += &s,
-                    TemplateValue::Interpolation(interpolation) => {
-                        str += format!("${{{}}}", stringify_value(interpolation, ident, minify))
-                            .as_str();
+                let unit = if (0xD800..0xDC00).contains(&high) {
+                    let low = hex_escape(&str[start + 6..])
+                        .filter(|low| (0xDC00..0xE000).contains(low))
+                        .ok_or_else(|| invalid(6))?;
+                    for _ in 0..6 {
+                        chars.next();
                     }
-                }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                unescaped.push(char::from_u32(unit).ok_or_else(|| invalid(6))?);
             }
-            str + "`"
+            Some(c) => unescaped.push(c),
+            None => return Err(invalid(1)),
         }
+    }
+
+    Ok(unescaped)
+}
+
+/// Code unit of the `\uXXXX` escape at the start of `str`
+fn hex_escape(str: &str) -> Option<u32> {
+    let digits = str.strip_prefix("\\u")?.get(..4)?;
+    u32::from_str_radix(digits, 16).ok()
+}
 
-        Value::ReferenceDeclaration { id, value } => {
-            let whitespace = if minify { "" } else { " " };
-            format!(
-                "#{{{}#id:{}{};{}#value:{}{};{}}}",
-                whitespace,
-                whitespace,
-                stringify_value(Value::String(id), ident, minify),
-                whitespace,
-                whitespace,
-                stringify_value(*value, ident, minify),
-                whitespace,
-            )
+/// Whether the key can be written without quotes
+pub(crate) fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() || first == '$' || first == '_' => {
+            chars.all(|c| c.is_alphabetic() || c.is_ascii_digit() || c == '$' || c == '_')
         }
-        Value::Reference(reference_value) => match reference_value {
-            ReferenceAsValue::Id(id) => {
-                format!("#\"{}\"", sanitize_string(id))
-            }
-            ReferenceAsValue::Path(path) => {
-                let mut sanitized_path: Vec<String> = vec![];
-                for e in path {
-                    sanitized_path.push(format!("\"{}\"", sanitize_string(e)));
-                }
-                format!("#/{}", sanitized_path.join("/"))
+        _ => false,
+    }
+}
+
+/// Whether the reference path segment can be written as an array index: `0`, `12`
+pub(crate) fn is_index(segment: &str) -> bool {
+    segment == "0"
+        || (!segment.is_empty()
+            && !segment.starts_with('0')
+            && segment.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Number literal that is read back as a float: `2.0`, `0.5`, `1e21`.
+/// Integer literals are parsed as [`Value::Integer`]
+pub(crate) fn float_literal(number: f64) -> String {
+    // `Debug` keeps `.0` of whole numbers and uses an exponent for large and small ones
+    format!("{:?}", number)
+}
+
+/// Escapes a string to be written between `quote`s (`"`, `'` or `` ` ``), the opposite of [`unescape`]
+/// ## Example:
+/// ```
+/// escape("it's\n", '\'') // "it\\'s\\n"
+/// ```
+pub fn escape(str: &str, quote: char) -> String {
+    let mut escaped = String::with_capacity(str.len());
+    let mut chars = str.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            '\u{8}' => escaped += "\\b",
+            '\u{c}' => escaped += "\\f",
+            // `${` starts an interpolation in template strings
+            '$' if quote == '`' && chars.peek() == Some(&'{') => escaped += "\\u0024",
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
             }
-        },
+            c if c.is_control() => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
 
-        _ => String::new(),
+pub fn stringify_value(value: Value, ident: usize, minify: bool) -> String {
+    stringify_json::stringify(value, ident, minify)
+}
+
+/// Text of a value put into a string: strings are not quoted
+/// and whole floats are written like integers, `80` instead of `80.0`
+pub(crate) fn text(value: Value, minify: bool) -> String {
+    match value {
+        Value::String(str) | Value::Identifier(str) => str,
+        Value::Number(number) => number.to_string(),
+        value => stringify_value(value, 0, minify),
     }
 }
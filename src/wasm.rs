@@ -1,7 +1,9 @@
-use std::collections::HashMap;
-
-use crate::{parser, stringify_json, ReferenceAsValue, TemplateValue, Value};
-use js_sys::{Array, Number, Object, Reflect};
+use crate::stringify_json::{FormatOptions, Indent, KeyQuotes, Quote};
+use crate::{
+    parser, stringify_json, Conversion, IndexMap, Path, ReferenceAsValue, TemplateValue, Value,
+    Variable,
+};
+use js_sys::{Array, BigInt, Number, Object, Reflect};
 use wasm_bindgen::prelude::*;
 
 fn value_to_jsvalue(value: Value, ident: usize, minify: bool) -> JsValue {
@@ -13,6 +15,10 @@ fn value_to_jsvalue(value: Value, ident: usize, minify: bool) -> JsValue {
         Value::Null => JsValue::null(),
         Value::Boolean(b) => JsValue::from_bool(b),
         Value::Number(n) => JsValue::from_f64(n),
+        Value::Integer(_) | Value::BigInteger(_) => match value.as_f64() {
+            Some(Conversion::Exact(n)) => JsValue::from_f64(n),
+            _ => big_int(&value),
+        },
         Value::Object(hashmap) => {
             let obj = Object::new();
             for (k, v) in hashmap {
@@ -29,6 +35,19 @@ fn value_to_jsvalue(value: Value, ident: usize, minify: bool) -> JsValue {
     }
 }
 
+/// JavaScript `BigInt` of an integer that is not exact as a number
+fn big_int(value: &Value) -> JsValue {
+    let digits = match value {
+        Value::Integer(integer) => integer.to_string(),
+        Value::BigInteger(integer) => integer.clone(),
+        _ => return JsValue::null(),
+    };
+    match BigInt::new(&JsValue::from(digits.as_str())) {
+        Ok(integer) => JsValue::from(integer),
+        Err(_) => JsValue::null(),
+    }
+}
+
 // #[wasm_bindgen]
 // extern "C" {
 //     fn alert(s: &str);
@@ -49,6 +68,12 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
         } else {
             Ok(Value::Number(value.as_f64().unwrap()))
         }
+    } else if value.is_bigint() {
+        let digits: String = BigInt::from(value).to_string(10).unwrap().into();
+        match digits.parse::<i128>() {
+            Ok(integer) => Ok(Value::Integer(integer)),
+            Err(_) => Ok(Value::BigInteger(digits)),
+        }
     } else if value.as_bool().is_some() {
         Ok(Value::Boolean(value.as_bool().unwrap()))
     } else if value.is_array() {
@@ -60,7 +85,7 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
         Ok(Value::Array(vec))
     } else if value.is_object() {
         let obj: Object = value.dyn_into().unwrap();
-        let mut hashmap: HashMap<String, Value> = HashMap::new();
+        let mut hashmap: IndexMap<String, Value> = IndexMap::new();
         for key in Object::keys(&obj) {
             if key.as_string().is_some() {
                 let val = Reflect::get(&obj, &key);
@@ -102,7 +127,10 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
                 }
             }
             return Ok(Value::Reference(ReferenceAsValue::Id(_id_str)));
-        } else if hashmap.len() == 1 && hashmap.contains_key("#reference_path") {
+        } else if hashmap.contains_key("#reference_path")
+            && (hashmap.len() == 1
+                || (hashmap.len() == 2 && hashmap.contains_key("#reference_parents")))
+        {
             let path_val = hashmap.get("#reference_path").unwrap();
             let mut path_arr: Vec<String> = Vec::new();
             match path_val {
@@ -110,6 +138,11 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
                     for e in arr {
                         match e {
                             Value::String(s) => path_arr.push(s.to_string()),
+                            // Array index
+                            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => {
+                                path_arr.push(n.to_string())
+                            }
+                            Value::Integer(n) if *n >= 0 => path_arr.push(n.to_string()),
                             _ => {}
                         }
                     }
@@ -118,7 +151,28 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
                     return Err("Path must be array".to_string());
                 }
             }
-            return Ok(Value::Reference(ReferenceAsValue::Path(path_arr)));
+            let path = Path::from(path_arr);
+            return match hashmap.get("#reference_parents") {
+                None => Ok(Value::Reference(ReferenceAsValue::Path(path))),
+                Some(parents) => match parents.as_u64().and_then(Conversion::exact) {
+                    Some(parents) => Ok(Value::Reference(ReferenceAsValue::Relative {
+                        parents: parents as usize,
+                        path,
+                    })),
+                    _ => Err("Parents must be a non-negative integer".to_string()),
+                },
+            };
+        } else if hashmap.len() == 1 && hashmap.contains_key("#include") {
+            return match hashmap.get("#include").unwrap() {
+                Value::String(name) => Ok(Value::Include(name.to_string())),
+                _ => Err("Include must be string".to_string()),
+            };
+        } else if hashmap.len() == 1 && hashmap.contains_key("@expression") {
+            return match hashmap.get("@expression").unwrap() {
+                Value::String(expression) => parser::parse_raw(format!("$({})", expression))
+                    .map_err(|e| format!("Invalid expression: {}", e)),
+                _ => Err("Expression must be string".to_string()),
+            };
         } else if hashmap.len() == 1 && hashmap.contains_key("@template_string") {
             let template_value = hashmap.get("@template_string").unwrap();
             let mut template_vec: Vec<TemplateValue> = vec![];
@@ -129,6 +183,26 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
                             Value::String(str) => {
                                 template_vec.push(TemplateValue::String(str.to_string()))
                             }
+                            Value::Object(object)
+                                if object.len() == 1 && object.contains_key("#variable") =>
+                            {
+                                let variable = match object.get("#variable").unwrap() {
+                                    Value::String(variable) => variable.split_once(':'),
+                                    _ => None,
+                                };
+                                let (provider, name) = match variable {
+                                    Some(variable) => variable,
+                                    None => {
+                                        return Err("Variable must be `provider:name`".to_string())
+                                    }
+                                };
+                                let optional = provider.ends_with('?');
+                                template_vec.push(TemplateValue::Variable(Variable {
+                                    provider: provider.trim_end_matches('?').to_string(),
+                                    name: name.to_string(),
+                                    optional,
+                                }))
+                            }
                             _ => template_vec.push(TemplateValue::Interpolation(e.clone())),
                         }
                     }
@@ -149,14 +223,79 @@ fn jsvalue_to_value(value: JsValue) -> Result<Value, String> {
 fn parse_from_string(str: String, ident: usize, minify: bool) -> Result<JsValue, String> {
     match parser::parse(str) {
         Ok(v) => Ok(value_to_jsvalue(v, ident, minify)),
-        Err(e) => Err(e),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Reads options of `stringify`:
+/// `{ indent: 2 | "\t", quoteKeys: "always" | "needed", quote: "double" | "single",
+/// trailingCommas, maxWidth, finalNewline, sortKeys }`
+fn format_options(value: JsValue, minify: bool) -> Result<FormatOptions, String> {
+    let mut options = FormatOptions {
+        minify,
+        ..Default::default()
+    };
+    if value.is_undefined() || value.is_null() {
+        return Ok(options);
     }
+
+    let get = |key: &str| Reflect::get(&value, &JsValue::from(key)).unwrap_or(JsValue::UNDEFINED);
+
+    let indent = get("indent");
+    if let Some(width) = indent.as_f64() {
+        options.indent = Indent::Spaces(width as usize);
+    } else if indent.as_string().as_deref() == Some("\t") {
+        options.indent = Indent::Tabs;
+    } else if !indent.is_undefined() {
+        return Err("Indent must be number or \"\\t\"".to_string());
+    }
+
+    match get("quoteKeys").as_string().as_deref() {
+        None | Some("always") => {}
+        Some("needed") => options.key_quotes = KeyQuotes::WhenNeeded,
+        Some(_) => return Err("quoteKeys must be \"always\" or \"needed\"".to_string()),
+    }
+
+    match get("quote").as_string().as_deref() {
+        None | Some("double") => {}
+        Some("single") => options.quote = Quote::Single,
+        Some(_) => return Err("quote must be \"double\" or \"single\"".to_string()),
+    }
+
+    if let Some(trailing_commas) = get("trailingCommas").as_bool() {
+        options.trailing_commas = trailing_commas;
+    }
+    if let Some(max_width) = get("maxWidth").as_f64() {
+        options.max_width = Some(max_width as usize);
+    }
+    if let Some(final_newline) = get("finalNewline").as_bool() {
+        options.final_newline = final_newline;
+    }
+    if let Some(sort_keys) = get("sortKeys").as_bool() {
+        options.sort_keys = sort_keys;
+    }
+
+    Ok(options)
 }
 
 #[wasm_bindgen]
-pub fn stringify(val: JsValue, ident: usize, minify: bool) -> Result<String, JsError> {
+pub fn stringify(
+    val: JsValue,
+    ident: usize,
+    minify: bool,
+    options: JsValue,
+) -> Result<String, JsError> {
+    let options = match format_options(options, minify) {
+        Ok(options) => options,
+        Err(e) => return Err(JsError::new(e.as_str())),
+    };
     match jsvalue_to_value(val) {
-        Ok(v) => Ok(stringify_json::stringify(v, ident, minify)),
+        Ok(v) => Ok(stringify_json::stringify_indented(
+            v,
+            &" ".repeat(ident),
+            stringify_json::NEWLINE,
+            &options,
+        )),
         Err(e) => Err(JsError::new(e.as_str())),
     }
 }
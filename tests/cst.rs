@@ -0,0 +1,62 @@
+use fson::{
+    cst::{CstElement, CstNode, TokenKind},
+    parser::parse_cst,
+    Rule,
+};
+
+const DOCUMENT: &str = "// Server
+{
+  /* address */ host: 'localhost',
+  \"port\": 0x50,
+  url: `http://${#/host}:${#/port}`,
+  id: #{ #id: \"id\"; #value: +1.50e2; },
+  list: [#id, #/\"host\", NaN, -Infinity, ]
+}
+";
+
+fn find(node: &CstNode, rule: Rule) -> Vec<&CstNode> {
+    let mut found: Vec<&CstNode> = vec![];
+    if node.rule == rule {
+        found.push(node);
+    }
+    for e in node.nodes() {
+        found.extend(find(e, rule));
+    }
+    found
+}
+
+#[test]
+fn lossless() {
+    let cst = parse_cst(String::from(DOCUMENT)).unwrap();
+    assert_eq!(cst.to_string(), DOCUMENT);
+
+    let comments: Vec<&str> = find(&cst, Rule::object)[0]
+        .tokens()
+        .chain(cst.tokens())
+        .filter(|token| token.kind == TokenKind::Comment)
+        .map(|token| token.text.as_str())
+        .collect();
+    assert_eq!(comments, vec!["/* address */", "// Server"]);
+}
+
+#[test]
+fn literal_spelling() {
+    let cst = parse_cst(String::from(DOCUMENT)).unwrap();
+
+    let numbers: Vec<String> = find(&cst, Rule::number)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(numbers, vec!["0x50", "+1.50e2", "-Infinity"]);
+
+    let quotes: Vec<Option<char>> = find(&cst, Rule::string).iter().map(|e| e.quote()).collect();
+    assert_eq!(quotes, vec![Some('\''), Some('"'), Some('`'), Some('"')]);
+
+    let arrays = find(&cst, Rule::array);
+    assert!(arrays[0].has_trailing_comma());
+    assert!(!find(&cst, Rule::object)[0].has_trailing_comma());
+
+    let port = find(&cst, Rule::object_pair)[1];
+    assert_eq!(port.span.line, 4);
+    assert!(matches!(&port.children[0], CstElement::Node(key) if key.to_string() == "\"port\""));
+}
@@ -0,0 +1,143 @@
+use fson::{document::Document, parser::parse, Error, SemanticErrorKind, Value};
+
+const DOCUMENT: &str = "// Server
+{
+  host: #{ #id: 'host'; #value: 'localhost'; }, // main host
+  'port': 0x50,
+  list: [
+    1,
+    2
+  ],
+  inline: { a: 1, b: 2 }
+}
+";
+
+#[test]
+fn edit() {
+    let mut document = Document::parse(String::from(DOCUMENT)).unwrap();
+    document.set(&["port"], Value::Integer(8080)).unwrap();
+    document
+        .set(&["host"], Value::String(String::from("example.com")))
+        .unwrap();
+    document.set(&["tls"], Value::Boolean(true)).unwrap();
+    document.set(&["inline", "c"], Value::Integer(3)).unwrap();
+    document
+        .insert_array(&["list"], 0, Value::Integer(0))
+        .unwrap();
+    document.remove(&["list", "2"]).unwrap();
+    document.remove(&["inline", "a"]).unwrap();
+    document.rename_key(&["port"], "server port").unwrap();
+
+    assert_eq!(
+        document.to_string(),
+        "// Server
+{
+  host: #{ #id: 'host'; #value: 'example.com'; }, // main host
+  'server port': 8080,
+  list: [
+    0,
+    1
+  ],
+  inline: { b: 2, c: 3 },
+  tls: true
+}
+"
+    );
+}
+
+#[test]
+fn edit_errors() {
+    let mut document = Document::parse(String::from(DOCUMENT)).unwrap();
+
+    match document.remove(&["server", "port"]) {
+        Err(Error::Semantic {
+            kind: SemanticErrorKind::PathNotFound(path),
+            ..
+        }) => assert_eq!(path, "/server"),
+        other => panic!("{:?}", other),
+    }
+    match document.insert_array(&["list"], 5, Value::Null) {
+        Err(Error::Semantic {
+            kind: SemanticErrorKind::IndexOutOfBounds { index, len },
+            ..
+        }) => assert_eq!((index, len), (5, 2)),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        document
+            .rename_key(&["list"], "host")
+            .unwrap_err()
+            .to_string(),
+        "5:3: duplicate key `host`, first used at 3:3"
+    );
+    assert_eq!(document.to_string(), DOCUMENT);
+}
+
+#[test]
+fn edit_style() {
+    let mut document = Document::parse(String::from("{ a: { b: 1 } }")).unwrap();
+    let mut object = fson::IndexMap::new();
+    object.insert(String::from("x"), Value::Integer(1));
+    document
+        .set(&["a", "c"], Value::Object(object.clone()))
+        .unwrap();
+    assert_eq!(document.to_string(), "{ a: { b: 1, c: { x: 1 } } }");
+
+    let mut document =
+        Document::parse(String::from("{\n    'name': 'api',\n    'ports': []\n}")).unwrap();
+    document.set(&["db"], Value::Object(object)).unwrap();
+    document
+        .set(&["ports", "0"], Value::String(String::from("http")))
+        .unwrap();
+    assert_eq!(
+        document.to_string(),
+        "{\n    'name': 'api',\n    'ports': ['http'],\n    'db': {\n        'x': 1\n    }\n}"
+    );
+
+    // Comments of a container stay when its only item is removed
+    let mut document = Document::parse(String::from(
+        "{\n  list: [\n    // none yet\n    1\n  ],\n  b: { c: 1 }\n}",
+    ))
+    .unwrap();
+    document.remove(&["list", "0"]).unwrap();
+    document.remove(&["b", "c"]).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "{\n  list: [\n    // none yet\n  ],\n  b: {}\n}"
+    );
+
+    // Nested values are indented with the tabs and line endings of the document
+    let value = parse(String::from("{ x: { y: [1, { z: 2 }] } }")).unwrap();
+    let mut document =
+        Document::parse(String::from("{\n\t\"a\": {\n\t\t\"b\": 1\n\t}\n}")).unwrap();
+    document.set(&["a", "c"], value.clone()).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "{\n\t\"a\": {\n\t\t\"b\": 1,\n\t\t\"c\": {\n\t\t\t\"x\": {\n\t\t\t\t\"y\": [1, {\n\t\t\t\t\t\"z\": 2\n\t\t\t\t}]\n\t\t\t}\n\t\t}\n\t}\n}"
+    );
+
+    let mut document = Document::parse(String::from("{\r\n  a: 1\r\n}")).unwrap();
+    document.set(&["b"], value).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "{\r\n  a: 1,\r\n  b: {\r\n    x: {\r\n      y: [1, {\r\n        z: 2\r\n      }]\r\n    }\r\n  }\r\n}"
+    );
+}
+
+#[test]
+fn edit_target() {
+    // The root declaration is edited like nested ones
+    let mut document =
+        Document::parse(String::from("#{ #id: 'config'; #value: { a: 1 }; }")).unwrap();
+    document.set(&[], Value::Integer(2)).unwrap();
+    assert_eq!(document.to_string(), "#{ #id: 'config'; #value: 2; }");
+
+    // The last one of duplicate keys is the one parsing keeps
+    let mut document = Document::parse(String::from("{ a: 1, a: 2 }")).unwrap();
+    document.set(&["a"], Value::Integer(3)).unwrap();
+    assert_eq!(document.to_string(), "{ a: 1, a: 3 }");
+    assert_eq!(
+        parse(document.to_string()).unwrap(),
+        parse(String::from("{ a: 3 }")).unwrap()
+    );
+}
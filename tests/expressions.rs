@@ -0,0 +1,321 @@
+use fson::{
+    generator, object,
+    parser::{parse, parse_raw, parse_with, FunctionRegistry, MapProvider, ParseOptions},
+    Arity, BinaryOperator, Error, Expression, ExpressionErrorKind, ReferenceAsValue, TemplateValue,
+    Value,
+};
+
+/// Document with `expression` in the template string of `value`
+fn document(expression: &str) -> String {
+    format!(
+        "{{ base_port: 80, replicas: 3, debug: true, name: 'api', value: `${{{}}}` }}",
+        expression
+    )
+}
+
+fn evaluate(expression: &str) -> Value {
+    match parse(document(expression)).unwrap() {
+        Value::Object(mut object) => object.swap_remove("value").unwrap(),
+        _ => panic!("expected object"),
+    }
+}
+
+fn error(expression: &str) -> Error {
+    parse(document(expression)).unwrap_err()
+}
+
+#[test]
+fn operators() {
+    let cases = [
+        ("#/base_port + 1", "81"),
+        ("#/replicas * 2", "6"),
+        ("#/debug ? 'verbose' : 'info'", "verbose"),
+        ("1 + 2 * 3 - 4 / 2", "5"),
+        ("(1 + 2) * 3", "9"),
+        ("7 / 2", "3.5"),
+        ("7 % 4 - -1", "4"),
+        ("#/name + ':' + #/base_port", "api:80"),
+        ("1 == 1.0 && 'a' < 'b'", "true"),
+        ("!#/debug || #/replicas >= 4", "false"),
+        ("#/replicas > 2 ? #/replicas : 2", "3"),
+        ("false ? 1 : true ? 2 : 3", "2"),
+    ];
+
+    for (expression, result) in cases {
+        assert_eq!(
+            evaluate(expression),
+            Value::String(String::from(result)),
+            "{}",
+            expression
+        );
+    }
+}
+
+#[test]
+fn expression_errors() {
+    let add = error("#/name + #/debug");
+    assert_eq!(add.to_string(), "1:67: cannot add string and bool");
+    match &add {
+        Error::Expression {
+            location: Some(location),
+            kind:
+                ExpressionErrorKind::Operands {
+                    operator: BinaryOperator::Add,
+                    ..
+                },
+        } => assert_eq!(location.snippet, "#/name + #/debug"),
+        _ => panic!("expected expression error, got {:?}", add),
+    }
+
+    assert_eq!(
+        error("#/replicas ? 1 : 2").to_string(),
+        "1:67: condition must be bool, found number"
+    );
+    assert_eq!(
+        error("#/replicas / (#/base_port - 80)").to_string(),
+        "1:67: division by zero"
+    );
+    assert_eq!(error("-#/name").to_string(), "1:67: cannot negate string");
+    // The right operand is not evaluated
+    assert_eq!(
+        evaluate("false && 1 / 0"),
+        Value::String(String::from("false"))
+    );
+
+    // Operands that are not evaluated can reference nothing
+    assert_eq!(
+        parse(String::from("$(false && #nope)")).unwrap(),
+        Value::Boolean(false)
+    );
+    assert_eq!(
+        parse(String::from("$(true ? 1 : #nope)")).unwrap(),
+        Value::Integer(1)
+    );
+    assert_eq!(
+        parse(String::from("$(false ? 1 : #nope)"))
+            .unwrap_err()
+            .to_string(),
+        "1:15: unresolved reference `#nope`"
+    );
+
+    let mut options = ParseOptions::default();
+    options.providers.insert(
+        "env",
+        MapProvider::from_iter([("PORT", Value::String(String::from("8080")))]),
+    );
+    assert_eq!(
+        parse_with(String::from("`${number(env:PORT)-1}`"), &options).unwrap(),
+        Value::String(String::from("8079"))
+    );
+}
+
+#[test]
+fn raw_expressions() {
+    let value = parse_raw(String::from("`${#port + 1}`")).unwrap();
+    assert_eq!(
+        value,
+        Value::TemplateString(vec![TemplateValue::Interpolation(Value::Expression(
+            Box::new(Expression::Binary {
+                operator: BinaryOperator::Add,
+                left: Value::Reference(ReferenceAsValue::Id(String::from("port"))),
+                right: Value::Integer(1),
+            })
+        ))])
+    );
+
+    let source = "`${(#a + 1) * -#b - (2 - 3) ? #c ? 1 : 2 : !(#d || env?:X)}`";
+    let printed = generator::from(parse_raw(String::from(source)).unwrap());
+    assert_eq!(
+        printed,
+        "`${(#\"a\" + 1) * -#\"b\" - (2 - 3) ? #\"c\" ? 1 : 2 : !(#\"d\" || env?:X)}`"
+    );
+    assert_eq!(
+        parse_raw(printed).unwrap(),
+        parse_raw(String::from(source)).unwrap()
+    );
+}
+
+#[test]
+fn typed_expressions() {
+    let value = parse(String::from(
+        "{
+            base_port: 80,
+            port: $(#/base_port + 1),
+            debug: $(#./port != 81),
+            ports: $([#/base_port] + [#/port]),
+            db: $(#/debug ? null : { host: 'localhost' }),
+        }",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        value,
+        Value::Object(object!(
+            String::from("base_port") => Value::Integer(80),
+            String::from("port") => Value::Integer(81),
+            String::from("debug") => Value::Boolean(false),
+            String::from("ports") => Value::Array(vec![Value::Integer(80), Value::Integer(81)]),
+            String::from("db") => Value::Object(object!(
+                String::from("host") => Value::String(String::from("localhost"))
+            ))
+        ))
+    );
+
+    let raw = parse_raw(String::from("{ port: $(#base * 2), base: $( #base ) }")).unwrap();
+    assert_eq!(
+        generator::from(raw),
+        "{\n  \"port\": $(#\"base\" * 2),\n  \"base\": #\"base\",\n}"
+    );
+
+    let options = ParseOptions {
+        allow_expressions: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with(String::from("[$(1 + 1)]"), &options)
+            .unwrap_err()
+            .to_string(),
+        "1:2: expressions are not allowed"
+    );
+}
+
+#[test]
+fn functions() {
+    let cases = [
+        ("upper(#/name) + '-' + lower('X')", "API-x"),
+        ("replace(trim('  a.b  '), '.', '/')", "a/b"),
+        ("join(split('a,b,c', ','), ' ')", "a b c"),
+        ("len(#/name) + len([1, 2]) + len({ a: 1 })", "6"),
+        (
+            "join(keys({ a: 1, b: 2 }), ',') + join(values({ a: 1, b: 2 }), ',')",
+            "a,b1,2",
+        ),
+        (
+            "contains([80, 443], #/base_port) && contains('api', 'p')",
+            "true",
+        ),
+        ("min(#/replicas, 2) + max([1, 5, 3])", "7"),
+        ("round(2.5) + ' ' + round(3.14159, 2)", "3 3.14"),
+        ("number(' 0.5 ') + number('2') + number(1)", "3.5"),
+        ("default(null, 8080) + default(1, 2)", "8081"),
+        ("base64('fson:')", "ZnNvbjo="),
+        (
+            "json({ port: #/base_port, hosts: ['a'] })",
+            "{\"port\":80,\"hosts\":[\"a\"]}",
+        ),
+    ];
+
+    for (expression, result) in cases {
+        assert_eq!(
+            evaluate(expression),
+            Value::String(String::from(result)),
+            "{}",
+            expression
+        );
+    }
+
+    assert_eq!(
+        error("upper(#/base_port)").to_string(),
+        "1:67: `upper` expects string, found number"
+    );
+    assert_eq!(
+        error("1 + round(1, 2, 3)").to_string(),
+        "1:71: `round` takes 1 or 2 arguments, found 3"
+    );
+    assert_eq!(
+        error("lenght(#/name)").to_string(),
+        "1:67: unknown function `lenght`"
+    );
+    assert_eq!(
+        error("number('80a')").to_string(),
+        "1:67: `number` expects number or numeric string, found string"
+    );
+    assert_eq!(
+        error("max([])").to_string(),
+        "1:67: `max` expects non-empty array, found array"
+    );
+
+    // Integers rounded to tens and hundreds stay integers
+    assert_eq!(
+        parse(String::from(
+            "[$(round(1234, -2)), $(round(-1250, -2)), $(round(5, -1))]"
+        ))
+        .unwrap(),
+        Value::Array(vec![
+            Value::Integer(1200),
+            Value::Integer(-1300),
+            Value::Integer(10)
+        ])
+    );
+
+    let printed = generator::from(parse_raw(String::from("`${max( #a,1 ) * 2}`")).unwrap());
+    assert_eq!(printed, "`${max(#\"a\", 1) * 2}`");
+}
+
+#[test]
+fn host_functions() {
+    let mut options = ParseOptions::default();
+    options
+        .functions
+        .insert("region_endpoint", Arity::exact(1), |args| match &args[0] {
+            Value::String(region) if region == "eu" || region == "us" => {
+                Ok(Value::String(format!("https://{}.example.com", region)))
+            }
+            _ => Err(Error::Expression {
+                location: None,
+                kind: ExpressionErrorKind::Failed(String::from("unknown region")),
+            }),
+        });
+    options.functions.insert("sum", Arity::at_least(0), |args| {
+        Ok(Value::Integer(
+            args.iter()
+                .filter_map(Value::as_i64)
+                .map(|n| n.value() as i128)
+                .sum(),
+        ))
+    });
+
+    let parse = |source: &str| parse_with(String::from(source), &options);
+    assert_eq!(
+        parse("`${region_endpoint('eu')}/api`").unwrap(),
+        Value::String(String::from("https://eu.example.com/api"))
+    );
+    assert_eq!(
+        parse("$(sum(1, 2, len('abc')))").unwrap(),
+        Value::Integer(6)
+    );
+
+    assert_eq!(
+        parse("`${region_endpoint('mars')}`")
+            .unwrap_err()
+            .to_string(),
+        "1:4: unknown region"
+    );
+    assert_eq!(
+        parse("`${region_endpoint()}`").unwrap_err().to_string(),
+        "1:4: `region_endpoint` takes 1 argument, found 0"
+    );
+    let error = parse("`${region_endpoit('eu')}`").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "1:4: unknown function `region_endpoit`, did you mean `region_endpoint`?"
+    );
+    assert!(matches!(
+        error,
+        Error::Expression {
+            kind: ExpressionErrorKind::UnknownFunction {
+                suggestion: Some(_),
+                ..
+            },
+            ..
+        }
+    ));
+
+    options.functions = FunctionRegistry::empty();
+    assert_eq!(
+        parse_with(String::from("$(upper('a'))"), &options)
+            .unwrap_err()
+            .to_string(),
+        "1:3: unknown function `upper`"
+    );
+}
@@ -1,6 +1,192 @@
-use fson::{generator, Value};
+use fson::{
+    generator,
+    json::{self, JsonOptions, NodePolicy, NonFinitePolicy},
+    object,
+    parser::{parse, parse_raw},
+    stringify_json::{stringify_with, FormatOptions, Indent, KeyQuotes, Quote},
+    utils::{escape, unescape},
+    Value,
+};
 
 #[test]
 fn create() {
     assert_eq!(generator::from(Value::Null), String::from("null"));
 }
+
+#[test]
+fn raw_round_trip() {
+    let fson = String::from(
+        "{
+      server: { host: #{ #id: 'host'; #value: 'localhost'; }, port: 80 },
+      url: `http://${#host}:${#/server/port}/`
+    }",
+    );
+    let generated = generator::from(parse_raw(fson.clone()).unwrap());
+
+    assert!(generated.contains("#{ #id: \"host\"; #value: \"localhost\"; }"));
+    assert!(generated.contains("`http://${#\"host\"}:${#/\"server\"/\"port\"}/`"));
+    assert_eq!(parse(generated).unwrap(), parse(fson).unwrap());
+
+    let path = parse_raw(String::from("#/list/0")).unwrap();
+    let options = FormatOptions {
+        key_quotes: KeyQuotes::WhenNeeded,
+        ..Default::default()
+    };
+    assert_eq!(generator::from(path.clone()), "#/\"list\"/\"0\"");
+    assert_eq!(generator::from_with(path, &options), "#/list/0");
+
+    let relative = parse_raw(String::from("[#./a, #../../'b c'/0]")).unwrap();
+    assert_eq!(
+        generator::from_with(relative, &options),
+        "[#./a, #../../\"b c\"/0]"
+    );
+}
+
+#[test]
+fn string_round_trip() {
+    let strings = [
+        "plain",
+        "\"double\" 'single' `backtick` \\ /",
+        "\u{0}\u{1f}\u{7f}\u{8}\u{c}\n\r\t",
+        "${not interpolation} $ {} é 😀",
+    ];
+    for quote in ['"', '\'', '`'] {
+        for str in strings {
+            assert_eq!(unescape(&escape(str, quote)).unwrap(), str);
+            let source = format!("{}{}{}", quote, escape(str, quote), quote);
+            assert_eq!(parse(source).unwrap(), Value::String(String::from(str)));
+        }
+    }
+
+    let value = Value::Array(
+        strings
+            .iter()
+            .map(|e| Value::String(e.to_string()))
+            .collect(),
+    );
+    for quote in [Quote::Double, Quote::Single] {
+        let options = FormatOptions {
+            quote,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse(generator::from_with(value.clone(), &options)).unwrap(),
+            value
+        );
+    }
+}
+
+#[test]
+fn key_order() {
+    let value = parse(String::from("{ b: 1, a: { d: 2, c: 3 }, 'x y': #/b }")).unwrap();
+    let keys = |value: &Value| match value {
+        Value::Object(object) => object.keys().cloned().collect::<Vec<String>>(),
+        _ => vec![],
+    };
+    assert_eq!(keys(&value), ["b", "a", "x y"]);
+    assert_eq!(
+        keys(&parse(generator::from(value.clone())).unwrap()),
+        ["b", "a", "x y"]
+    );
+
+    let created = Value::Object(object!(
+        String::from("z") => Value::Null,
+        String::from("y") => Value::Null
+    ));
+    assert_eq!(keys(&created), ["z", "y"]);
+
+    assert_eq!(
+        generator::from_sorted(value),
+        "{\n  \"a\": {\n    \"c\": 3,\n    \"d\": 2,\n  },\n  \"b\": 1,\n  \"x y\": 1,\n}"
+    );
+}
+
+#[test]
+fn format_options() {
+    let value = parse(String::from(
+        "{ name: \"it's\", 'port list': [8080, 8081], nested: { deep: [{ x: 1 }, 'long string value'] } }",
+    ))
+    .unwrap();
+
+    let options = FormatOptions {
+        indent: Indent::Tabs,
+        key_quotes: KeyQuotes::WhenNeeded,
+        quote: Quote::Single,
+        trailing_commas: false,
+        max_width: Some(30),
+        final_newline: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        generator::from_with(value.clone(), &options),
+        "{\n\tname: 'it\\'s',\n\t'port list': [8080, 8081],\n\tnested: {\n\t\tdeep: [\n\t\t\t{ x: 1 },\n\t\t\t'long string value'\n\t\t]\n\t}\n}\n"
+    );
+
+    let minified = FormatOptions {
+        minify: true,
+        trailing_commas: false,
+        ..Default::default()
+    };
+    let generated = stringify_with(value.clone(), &minified);
+    assert_eq!(
+        generated,
+        "{\"name\":\"it's\",\"port list\":[8080,8081],\"nested\":{\"deep\":[{\"x\":1},\"long string value\"]}}"
+    );
+    assert_eq!(parse(generated).unwrap(), value);
+}
+
+#[test]
+fn strict_json() {
+    let value = parse_raw(String::from(
+        "{ x: #{ #id: 'x'; #value: NaN; }, 'a b': `\\t€ ${#x} 😀`, list: [#/x, Infinity] }",
+    ))
+    .unwrap();
+    let minified = |non_finite, nodes| JsonOptions {
+        non_finite,
+        nodes,
+        minify: true,
+        escape_unicode: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        json::stringify(
+            value.clone(),
+            &minified(NonFinitePolicy::Null, NodePolicy::Resolve)
+        )
+        .unwrap(),
+        "{\"x\":null,\"a b\":\"\\t\\u20ac NaN \\ud83d\\ude00\",\"list\":[null,null]}"
+    );
+    assert_eq!(
+        json::stringify(value.clone(), &minified(NonFinitePolicy::String, NodePolicy::Inline)).unwrap(),
+        "{\"x\":{\"#id\":\"x\",\"#value\":\"NaN\"},\"a b\":{\"@template_string\":[\"\\t\\u20ac \",{\"#reference_id\":\"x\"},\" \\ud83d\\ude00\"]},\"list\":[{\"#reference_path\":[\"x\"]},\"Infinity\"]}"
+    );
+    assert_eq!(
+        json::stringify(value.clone(), &JsonOptions::default())
+            .unwrap_err()
+            .to_string(),
+        "NaN at `/x` can not be represented in JSON"
+    );
+    assert_eq!(
+        json::stringify(value, &minified(NonFinitePolicy::Null, NodePolicy::Error))
+            .unwrap_err()
+            .to_string(),
+        "reference declaration at `/x` can not be represented in JSON"
+    );
+    assert_eq!(
+        json::stringify(
+            Value::String(String::from("\"\\\u{1}")),
+            &JsonOptions::default()
+        )
+        .unwrap(),
+        "\"\\\"\\\\\\u0001\""
+    );
+    assert_eq!(
+        json::stringify(
+            parse(String::from("{ a: [1, { b: '' }] }")).unwrap(),
+            &JsonOptions::default()
+        )
+        .unwrap(),
+        "{\n  \"a\": [\n    1,\n    {\n      \"b\": \"\"\n    }\n  ]\n}"
+    );
+}
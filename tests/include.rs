@@ -0,0 +1,124 @@
+use fson::{
+    object,
+    parser::{parse, parse_file, FileResolver, MapResolver, ParseOptions},
+    Error, IncludeErrorKind, Value,
+};
+
+#[test]
+fn nested_includes() {
+    let files = MapResolver::from_iter([
+        (
+            "main.fson",
+            "{ db: #include 'db/main.fson', url: `${#db_host}:${#/db/port}` }",
+        ),
+        (
+            "db/main.fson",
+            "{ host: #{ #id: 'db_host'; #value: 'localhost'; }, port: #include \"./port.fson\" }",
+        ),
+        ("db/port.fson", "5432"),
+    ]);
+
+    assert_eq!(
+        parse_file("main.fson", &files, &ParseOptions::default()).unwrap(),
+        Value::Object(object!(
+            String::from("db") => Value::Object(object!(
+                String::from("host") => Value::String(String::from("localhost")),
+                String::from("port") => Value::Integer(5432)
+            )),
+            String::from("url") => Value::String(String::from("localhost:5432"))
+        ))
+    );
+}
+
+#[test]
+fn diamond_includes() {
+    let files = MapResolver::from_iter([
+        (
+            "main.fson",
+            "{ a: #include 'a.fson', b: #include 'b.fson', host: #host }",
+        ),
+        ("a.fson", "{ common: #include 'common.fson' }"),
+        ("b.fson", "[#include 'common.fson']"),
+        ("common.fson", "#{ #id: 'host'; #value: 'localhost'; }"),
+    ]);
+    let host = || Value::String(String::from("localhost"));
+
+    assert_eq!(
+        parse_file("main.fson", &files, &ParseOptions::default()).unwrap(),
+        Value::Object(object!(
+            String::from("a") => Value::Object(object!(String::from("common") => host())),
+            String::from("b") => Value::Array(vec![host()]),
+            String::from("host") => host()
+        ))
+    );
+}
+
+#[test]
+fn include_errors() {
+    let options = ParseOptions::default();
+    let files = MapResolver::from_iter([
+        ("a.fson", "{ b: #include 'b.fson' }"),
+        ("b.fson", "[#include 'a.fson']"),
+        ("missing.fson", "{ x: #include 'nothing.fson' }"),
+        ("broken.fson", "{ x: #include 'syntax.fson' }"),
+        ("syntax.fson", "{\n  x: [1 2]\n}"),
+    ]);
+
+    let error = parse_file("a.fson", &files, &options).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "b.fson:1:2: include cycle `a.fson -> b.fson -> a.fson`"
+    );
+
+    let error = parse_file("missing.fson", &files, &options).unwrap_err();
+    match &error {
+        Error::Include {
+            location: Some(location),
+            kind: IncludeErrorKind::Load { name, .. },
+        } => {
+            assert_eq!(name, "nothing.fson");
+            assert_eq!(location.snippet, "#include 'nothing.fson'");
+        }
+        _ => panic!("expected include error, got {:?}", error),
+    }
+
+    let error = parse_file("broken.fson", &files, &options).unwrap_err();
+    assert_eq!(error.location().unwrap().span.file, 1);
+    assert_eq!(
+        error.to_string(),
+        "syntax.fson:2:9: expected `,` or `]`, found `2`"
+    );
+
+    let error = parse(String::from("{ x: #include 'a.fson' }")).unwrap_err();
+    assert!(matches!(
+        error,
+        Error::Include {
+            kind: IncludeErrorKind::NoResolver,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn file_resolver() {
+    let resolver = FileResolver::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/include"));
+
+    assert_eq!(
+        parse_file("main.fson", &resolver, &ParseOptions::default()).unwrap(),
+        Value::Object(object!(
+            String::from("name") => Value::String(String::from("server")),
+            String::from("ports") => Value::Array(vec![Value::Integer(80), Value::Integer(443)])
+        ))
+    );
+
+    // Paths can not leave the base directory
+    let resolver = FileResolver::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/include/shared"));
+    let error = parse_file("../main.fson", &resolver, &ParseOptions::default()).unwrap_err();
+    match &error {
+        Error::Include {
+            kind: IncludeErrorKind::Load { message, .. },
+            ..
+        } => assert!(message.contains("is outside of"), "{}", message),
+        _ => panic!("expected include error, got {:?}", error),
+    }
+}
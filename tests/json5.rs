@@ -0,0 +1,56 @@
+use fson::parser::{parse_with, Dialect, ParseOptions};
+use std::fs;
+use std::path::Path;
+
+/// Sources of the files with `extension` in `tests/json5/<dir>`
+fn corpus(dir: &str, extension: &str) -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/json5")
+        .join(dir);
+    let mut files: Vec<(String, String)> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().unwrap() == extension)
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            (name, fs::read_to_string(path).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn valid() {
+    let files = corpus("valid", "json5");
+    assert!(!files.is_empty());
+
+    let json5 = ParseOptions::dialect(Dialect::Json5);
+    let json = ParseOptions::dialect(Dialect::Json);
+
+    for (name, source) in files {
+        let value = parse_with(source, &json5).unwrap_or_else(|e| panic!("{}: {}", name, e));
+
+        let expected = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/json5/valid")
+            .join(name.replace(".json5", ".json"));
+        if let Ok(expected) = fs::read_to_string(expected) {
+            assert_eq!(value, parse_with(expected, &json).unwrap(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn invalid() {
+    let files = corpus("invalid", "json5");
+    assert!(!files.is_empty());
+
+    let json5 = ParseOptions::dialect(Dialect::Json5);
+    for (name, source) in files {
+        assert!(
+            parse_with(source, &json5).is_err(),
+            "{} should not parse",
+            name
+        );
+    }
+}
@@ -1,4 +1,13 @@
-use fson::{object, parser::parse, Value};
+use fson::{
+    ast::{Node, TemplateNode},
+    generator, object,
+    parser::{
+        parse, parse_raw, parse_spanned, parse_with, Dialect, DuplicatePolicy, EnvProvider,
+        Feature, MapProvider, ParseOptions,
+    },
+    Conversion, Error, Location, Path, ReferenceAsValue, ReferenceErrorKind, SemanticErrorKind,
+    Span, TemplateValue, Value, Variable, VariableErrorKind,
+};
 
 #[test]
 fn basic() {
@@ -16,3 +25,568 @@ fn basic() {
         ))
     );
 }
+
+#[test]
+fn syntax_error() {
+    let error = parse(String::from("{\n  x: [1 2]\n}")).unwrap_err();
+
+    match error {
+        Error::Syntax { location, expected } => {
+            assert_eq!((location.span.line, location.span.column), (2, 9));
+            assert_eq!(location.span.start, 10);
+            assert_eq!(location.snippet, "2");
+            assert_eq!(expected, vec!["`,`", "`]`"]);
+        }
+        _ => panic!("expected syntax error, got {:?}", error),
+    }
+
+    assert_eq!(
+        parse(String::from("$(max(1 2))")).unwrap_err().to_string(),
+        "1:9: expected `,`, `)` or operator, found `2`"
+    );
+}
+
+#[test]
+fn invalid_number() {
+    let error = parse(String::from("[0x100000000000000000000000000000000]")).unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::Semantic {
+            location: Some(Box::new(Location {
+                span: Span {
+                    start: 1,
+                    end: 36,
+                    line: 1,
+                    column: 2,
+                    file: 0
+                },
+                file: None,
+                snippet: String::from("0x100000000000000000000000000000000")
+            })),
+            kind: SemanticErrorKind::InvalidNumber(String::from(
+                "0x100000000000000000000000000000000"
+            ))
+        }
+    );
+}
+
+#[test]
+fn integers() {
+    assert_eq!(
+        parse(String::from(
+            "[9007199254740993, 18446744073709551615, -0x10, +0xFF, 1.0, 1e3]"
+        ))
+        .unwrap(),
+        Value::Array(vec![
+            Value::Integer(9007199254740993),
+            Value::Integer(18446744073709551615),
+            Value::Integer(-16),
+            Value::Integer(255),
+            Value::Number(1.0),
+            Value::Number(1000.0),
+        ])
+    );
+
+    let big = "[-0x100000000000000000000000000000000, 340282366920938463463374607431768211456]";
+    let options = ParseOptions {
+        big_integers: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_with(String::from(big), &options).unwrap(),
+        Value::Array(vec![
+            Value::BigInteger(String::from("-340282366920938463463374607431768211456")),
+            Value::BigInteger(String::from("340282366920938463463374607431768211456")),
+        ])
+    );
+
+    // `-0` keeps its sign
+    let zero = parse(String::from("-0")).unwrap();
+    assert!(matches!(zero, Value::Number(zero) if zero == 0.0 && zero.is_sign_negative()));
+    assert_eq!(generator::from(zero), "-0.0");
+    assert_eq!(parse(String::from("0")).unwrap(), Value::Integer(0));
+
+    // Whole floats are printed so that they are read back as floats
+    for float in [Value::Number(2.0), Value::Number(1e21)] {
+        assert_eq!(parse(generator::from(float.clone())).unwrap(), float);
+    }
+
+    assert_eq!(
+        parse(String::from("[340282366920938463463374607431768211456]"))
+            .unwrap_err()
+            .to_string(),
+        "1:2: invalid number `340282366920938463463374607431768211456`"
+    );
+
+    assert_eq!(
+        Value::Integer(9007199254740993).as_f64(),
+        Some(Conversion::Lossy(9007199254740992.0))
+    );
+    assert_eq!(Value::Integer(-16).as_i64(), Some(Conversion::Exact(-16)));
+    assert_eq!(Value::Integer(-16).as_u64(), None);
+    assert_eq!(Value::Number(2.5).as_u64(), Some(Conversion::Lossy(2)));
+    assert_eq!(
+        Value::BigInteger(String::from("340282366920938463463374607431768211456")).as_f64(),
+        Some(Conversion::Exact(2f64.powi(128)))
+    );
+}
+
+#[test]
+fn dialects() {
+    let json = ParseOptions::dialect(Dialect::Json);
+    let json5 = ParseOptions::dialect(Dialect::Json5);
+    let error = |source: &str, options: &ParseOptions| {
+        parse_with(String::from(source), options)
+            .unwrap_err()
+            .to_string()
+    };
+
+    let source = "{ \"a\": [1, -2.5e3, \"\\u00e9\\n\"], \"b\": null }";
+    assert!(parse_with(String::from(source), &json).is_ok());
+
+    assert_eq!(error("[1] // one", &json), "1:5: comments are not allowed");
+    assert_eq!(
+        error("{ a: 1 }", &json),
+        "1:3: unquoted keys are not allowed"
+    );
+    assert_eq!(
+        error("['a']", &json),
+        "1:2: single-quoted strings are not allowed"
+    );
+    assert_eq!(
+        error("[1, 2, ]", &json),
+        "1:6: trailing commas are not allowed"
+    );
+    assert_eq!(
+        error("[-NaN]", &json),
+        "1:2: `NaN` and `Infinity` are not allowed"
+    );
+    assert_eq!(
+        error("[1, .5]", &json),
+        "1:5: hexadecimal numbers, `+` signs and bare decimal points are not allowed"
+    );
+    assert_eq!(
+        error("[\"\\x41\"]", &json),
+        "1:3: JSON5 escapes, line continuations and unescaped control characters are not allowed"
+    );
+    assert_eq!(
+        error("[\"a\nb\"]", &json5),
+        "1:4: line breaks in strings are not allowed"
+    );
+    assert_eq!(
+        error("{ a: #{ #id: 'a'; #value: 1; }, b: #a }", &json5),
+        "1:6: references are not allowed"
+    );
+    assert_eq!(
+        error("{ a: `${1}` }", &json5),
+        "1:6: template strings are not allowed"
+    );
+
+    let json_with_comments = ParseOptions {
+        allow_comments: true,
+        ..ParseOptions::dialect(Dialect::Json)
+    };
+    assert_eq!(
+        parse_with(String::from("/* ok */ [1]"), &json_with_comments).unwrap(),
+        Value::Array(vec![Value::Integer(1)])
+    );
+
+    match parse_with(String::from("{\n  \"a\": 0x10\n}"), &json).unwrap_err() {
+        Error::Semantic {
+            location: Some(location),
+            kind: SemanticErrorKind::NotAllowed(feature),
+        } => {
+            assert_eq!(feature, Feature::NumberExtensions);
+            assert_eq!((location.span.line, location.span.column), (2, 8));
+            assert_eq!(location.snippet, "0x10");
+        }
+        error => panic!("{:?}", error),
+    }
+}
+
+#[test]
+fn duplicates() {
+    let options = |duplicate_keys, duplicate_ids| ParseOptions {
+        duplicate_keys,
+        duplicate_ids,
+        ..Default::default()
+    };
+    let keys = String::from("{ a: 1, b: 2, a: 3 }");
+    let ids = String::from("[#{ #id: 'x'; #value: 1; }, #{ #id: 'x'; #value: 2; }, #x]");
+
+    assert_eq!(
+        parse(keys.clone()).unwrap(),
+        Value::Object(object!(
+            String::from("a") => Value::Integer(3),
+            String::from("b") => Value::Integer(2)
+        ))
+    );
+    assert_eq!(
+        parse_with(
+            keys.clone(),
+            &options(DuplicatePolicy::FirstWins, DuplicatePolicy::Error)
+        )
+        .unwrap(),
+        Value::Object(object!(
+            String::from("a") => Value::Integer(1),
+            String::from("b") => Value::Integer(2)
+        ))
+    );
+    let error = parse_with(
+        keys,
+        &options(DuplicatePolicy::Error, DuplicatePolicy::Error),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "1:15: duplicate key `a`, first used at 1:3"
+    );
+
+    let error = parse(ids.clone()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "1:32: duplicate reference id `x`, first declared at 1:5"
+    );
+    let (first, last) = (Value::Integer(1), Value::Integer(2));
+    assert_eq!(
+        parse_with(
+            ids.clone(),
+            &options(DuplicatePolicy::LastWins, DuplicatePolicy::FirstWins)
+        )
+        .unwrap(),
+        Value::Array(vec![first.clone(), last.clone(), first])
+    );
+    assert_eq!(
+        parse_with(
+            ids,
+            &options(DuplicatePolicy::LastWins, DuplicatePolicy::LastWins)
+        )
+        .unwrap(),
+        Value::Array(vec![Value::Integer(1), last.clone(), last])
+    );
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(
+        parse(String::from(
+            r#"["\"\\\/\b\f\n\r\t", 'it\'s é', `\` 😀 ${1}\n`]"#
+        ))
+        .unwrap(),
+        Value::Array(vec![
+            Value::String(String::from("\"\\/\u{8}\u{c}\n\r\t")),
+            Value::String(String::from("it's é")),
+            Value::String(String::from("` 😀 1\n")),
+        ])
+    );
+    assert_eq!(
+        parse(String::from("{ 'a\\u0020b': '\\ud83d' }"))
+            .unwrap_err()
+            .to_string(),
+        "1:16: invalid escape sequence `\\ud83d`"
+    );
+}
+
+#[test]
+fn unresolved_reference() {
+    let error = parse(String::from(
+        "{ protocol: #{ #id: 'protocol'; #value: 'http'; }, url: #protocl }",
+    ))
+    .unwrap_err();
+
+    match error {
+        Error::Reference { location, kind } => {
+            assert_eq!(
+                kind,
+                ReferenceErrorKind::Unresolved(String::from("#protocl"))
+            );
+            assert_eq!(location.unwrap().span.start, 56);
+        }
+        _ => panic!("expected reference error, got {:?}", error),
+    }
+
+    assert_eq!(
+        parse_with(
+            String::from("[#/server/hots]"),
+            &ParseOptions {
+                unresolved_references_as_null: true,
+                ..Default::default()
+            }
+        )
+        .unwrap(),
+        Value::Array(vec![Value::Null])
+    );
+}
+
+#[test]
+fn forward_references() {
+    assert_eq!(
+        parse(String::from(
+            "{
+      url: `${#protocol}://${#/server/host}`,
+      protocol: #{ #id: 'protocol'; #value: #/server/protocol; },
+      server: { host: #host, protocol: 'http' },
+      hosts: [#{ #id: 'host'; #value: 'localhost'; }]
+    }"
+        ))
+        .unwrap(),
+        Value::Object(object!(
+          String::from("url") => Value::String(String::from("http://localhost")),
+          String::from("protocol") => Value::String(String::from("http")),
+          String::from("server") => Value::Object(object!(
+            String::from("host") => Value::String(String::from("localhost")),
+            String::from("protocol") => Value::String(String::from("http"))
+          )),
+          String::from("hosts") => Value::Array(vec![Value::String(String::from("localhost"))])
+        ))
+    );
+}
+
+#[test]
+fn array_paths() {
+    let value = parse(String::from(
+        "{
+      servers: [{ host: 'a' }, #{ #id: 'b'; #value: { host: 'b', ports: [80, 443] }; }],
+      hosts: [#/servers/0/host, #/servers/1/host, #/\"servers\"/\"1\"/ports/1]
+    }",
+    ))
+    .unwrap();
+    let Value::Object(object) = value else {
+        panic!("not an object")
+    };
+    assert_eq!(
+        object["hosts"],
+        Value::Array(vec![
+            Value::String(String::from("a")),
+            Value::String(String::from("b")),
+            Value::Integer(443)
+        ])
+    );
+    assert!(parse(String::from("[1, #/01]")).is_err());
+}
+
+#[test]
+fn relative_references() {
+    assert_eq!(
+        parse(String::from(
+            "{
+      web: { host: 'web.local', url: `http://${#./host}` },
+      db: { host: 'db.local', url: `http://${#./host}`, web: #../web/url },
+      list: [1, #./0, #{ #id: 'x'; #value: [#../0]; }]
+    }"
+        ))
+        .unwrap(),
+        parse(String::from(
+            "{
+      web: { host: 'web.local', url: 'http://web.local' },
+      db: { host: 'db.local', url: 'http://db.local', web: 'http://web.local' },
+      list: [1, 1, [1]]
+    }"
+        ))
+        .unwrap()
+    );
+
+    assert_eq!(
+        parse_raw(String::from("[#./a/0, #../../'b c']")).unwrap(),
+        Value::Array(vec![
+            Value::Reference(ReferenceAsValue::Relative {
+                parents: 0,
+                path: Path::from(vec![String::from("a"), String::from("0")])
+            }),
+            Value::Reference(ReferenceAsValue::Relative {
+                parents: 2,
+                path: Path::from(vec![String::from("b c")])
+            }),
+        ])
+    );
+    assert_eq!(
+        parse(String::from("{ a: #../b }")).unwrap_err().to_string(),
+        "1:6: unresolved reference `#../b`"
+    );
+}
+
+#[test]
+fn path_keys() {
+    assert_eq!(
+        parse(String::from(
+            "[{ 'a/b': 1, a: { b: 2, '~': 3, '': 4 } }, #/0/'a/b', #/0/a/b, #/0/a/'~', #/0/a/'']"
+        ))
+        .unwrap(),
+        parse(String::from(
+            "[{ 'a/b': 1, a: { b: 2, '~': 3, '': 4 } }, 1, 2, 3, 4]"
+        ))
+        .unwrap()
+    );
+
+    let path = Path::from(vec![String::from("a/b"), String::from("~"), String::new()]);
+    assert_eq!(path.to_string(), "/a~1b/~0/");
+    assert_eq!(Path::parse("/a~1b/~0/").unwrap(), path);
+    assert!(Path::parse("").unwrap().is_root());
+    assert!(Path::parse("a/b").is_err());
+    assert_eq!(
+        Path::parse("/a~2").unwrap_err().to_string(),
+        "invalid path `/a~2`"
+    );
+}
+
+#[test]
+fn reference_cycle() {
+    let error = parse(String::from(
+        "{ a: #/b, b: #{ #id: 'b'; #value: `${#c}`; }, c: #{ #id: 'c'; #value: #/a; } }",
+    ))
+    .unwrap_err();
+
+    match error {
+        Error::Reference { location, kind } => {
+            assert_eq!(
+                kind,
+                ReferenceErrorKind::Cycle(vec![
+                    String::from("#/b"),
+                    String::from("#c"),
+                    String::from("#/a"),
+                    String::from("#/b")
+                ])
+            );
+            assert_eq!(location.unwrap().span.start, 5);
+        }
+        _ => panic!("expected reference error, got {:?}", error),
+    }
+}
+
+#[test]
+fn raw() {
+    assert_eq!(
+        parse_raw(String::from(
+            "{
+      host: #{ #id: 'host'; #value: 'localhost'; },
+      url: `http://${#host}${#/path}`,
+      path: '/'
+    }"
+        ))
+        .unwrap(),
+        Value::Object(object!(
+          String::from("host") => Value::ReferenceDeclaration {
+            id: String::from("host"),
+            value: Box::new(Value::String(String::from("localhost")))
+          },
+          String::from("url") => Value::TemplateString(vec![
+            TemplateValue::String(String::from("http://")),
+            TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Id(String::from("host")))),
+            TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Path(Path::from(vec![String::from("path")]))))
+          ]),
+          String::from("path") => Value::String(String::from("/"))
+        ))
+    );
+}
+
+#[test]
+fn spanned() {
+    let node = parse_spanned(String::from(
+        "{\n  server: { port: 80 },\n  url: `a${#x}`\n}",
+    ))
+    .unwrap();
+
+    let pairs = match node.node {
+        Node::Object(pairs) => pairs,
+        _ => panic!("expected object"),
+    };
+
+    let (key, server) = &pairs[0];
+    assert_eq!(key.node, "server");
+    assert_eq!(key.span.unwrap().line, 2);
+    match &server.node {
+        Node::Object(server_pairs) => {
+            let port = server_pairs[0].1.span.unwrap();
+            assert_eq!(
+                (port.line, port.column, port.start, port.end),
+                (2, 19, 20, 22)
+            );
+        }
+        _ => panic!("expected object"),
+    }
+
+    match &pairs[1].1.node {
+        Node::TemplateString(parts) => {
+            assert_eq!(parts[0].node, TemplateNode::String(String::from("a")));
+            assert_eq!(parts[0].span.unwrap().column, 9);
+            let interpolation = parts[1].span.unwrap();
+            assert_eq!(
+                (
+                    interpolation.column,
+                    interpolation.end - interpolation.start
+                ),
+                (10, 5)
+            );
+        }
+        _ => panic!("expected template string"),
+    }
+
+    assert_eq!(
+        Value::from(parse_spanned(String::from("[#x]")).unwrap()),
+        Value::Array(vec![Value::Reference(ReferenceAsValue::Id(String::from(
+            "x"
+        )))])
+    );
+}
+
+#[test]
+fn variables() {
+    let error = parse(String::from("`${env?:PATH}`")).unwrap_err();
+    assert_eq!(error.to_string(), "1:2: unknown value provider `env`");
+
+    let mut options = ParseOptions::default();
+    options.providers.insert(
+        "env",
+        MapProvider::from_iter([
+            ("DB_HOST", Value::String(String::from("db"))),
+            ("DB_PORT", Value::Integer(5432)),
+        ]),
+    );
+
+    assert_eq!(
+        parse_with(
+            String::from("`${env:DB_HOST}:${env:DB_PORT}${env?:DB_PATH}`"),
+            &options
+        )
+        .unwrap(),
+        Value::String(String::from("db:5432"))
+    );
+
+    assert_eq!(
+        parse_raw(String::from("`${env?:DB_PATH}`")).unwrap(),
+        Value::TemplateString(vec![TemplateValue::Variable(Variable {
+            provider: String::from("env"),
+            name: String::from("DB_PATH"),
+            optional: true,
+        })])
+    );
+
+    let error = parse_with(String::from("{ url: `db:${env:DB_PATH}` }"), &options).unwrap_err();
+    match &error {
+        Error::Variable {
+            location: Some(location),
+            kind: VariableErrorKind::Unset { name, .. },
+        } => {
+            assert_eq!(name, "DB_PATH");
+            assert_eq!(location.snippet, "${env:DB_PATH}");
+            assert_eq!(location.span.column, 12);
+        }
+        _ => panic!("expected variable error, got {:?}", error),
+    }
+
+    let error = parse_with(String::from("`${vault:key}`"), &options).unwrap_err();
+    assert_eq!(error.to_string(), "1:2: unknown value provider `vault`");
+
+    options.providers.insert("env", EnvProvider);
+    assert_eq!(
+        parse_with(String::from("`${env?:FSON_UNSET_VARIABLE}`"), &options).unwrap(),
+        Value::String(String::new())
+    );
+}
+
+#[test]
+fn options_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ParseOptions>();
+}
@@ -0,0 +1,40 @@
+use fson::{object, Error, Path, ReferenceAsValue, ReferenceErrorKind, TemplateValue, Value};
+
+#[test]
+fn resolve() {
+    let value = Value::Object(object!(
+        String::from("port") => Value::ReferenceDeclaration {
+            id: String::from("port"),
+            value: Box::new(Value::Number(80.0))
+        },
+        String::from("address") => Value::TemplateString(vec![
+            TemplateValue::String(String::from("localhost:")),
+            TemplateValue::Interpolation(Value::Reference(ReferenceAsValue::Id(String::from("port"))))
+        ]),
+        String::from("copy") => Value::Reference(ReferenceAsValue::Path(Path::from(vec![String::from("address")])))
+    ));
+
+    assert_eq!(
+        value.resolve().unwrap(),
+        Value::Object(object!(
+            String::from("port") => Value::Number(80.0),
+            String::from("address") => Value::String(String::from("localhost:80")),
+            String::from("copy") => Value::String(String::from("localhost:80"))
+        ))
+    );
+}
+
+#[test]
+fn resolve_unresolved() {
+    let value = Value::Array(vec![Value::Reference(ReferenceAsValue::Path(Path::from(
+        vec![String::from("server"), String::from("host")],
+    )))]);
+
+    assert_eq!(
+        value.resolve(),
+        Err(Error::Reference {
+            location: None,
+            kind: ReferenceErrorKind::Unresolved(String::from("#/server/host"))
+        })
+    );
+}